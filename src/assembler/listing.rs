@@ -0,0 +1,161 @@
+//! Writes a `HavokScriptFile` as an editable listing that the assembler can parse back.
+//!
+//! The listing follows the layout of the regular disassembly, but without colors and with
+//! every value needed to rebuild the file: constants are listed by index, strings are quoted
+//! and child functions are nested between `[Function]` and `[End Function]`.
+
+use crate::loader::{
+    hs::HavokScriptFile,
+    hs_constant::{HSConstant, HSValue},
+    hs_function::HSFunction,
+    hs_opcodes::HSOpCode,
+    hs_structure::HSStructPrototype,
+};
+
+use std::fmt::{Display, Formatter};
+
+/// Editable, colorless listing of a `HavokScriptFile`.
+pub struct HSListing<'a> {
+    /// The file to list.
+    file: &'a HavokScriptFile,
+}
+
+impl<'a> HSListing<'a> {
+    #[must_use]
+    pub fn new(file: &'a HavokScriptFile) -> Self {
+        Self { file }
+    }
+
+    fn fmt_function(f: &mut Formatter<'_>, function: &HSFunction) -> std::fmt::Result {
+        writeln!(f, "[Function]")?;
+        writeln!(f, "- UpValue Count: {}", function.up_value_count)?;
+        writeln!(f, "- Parameter Count: {}", function.param_count)?;
+        writeln!(f, "- Variadic Argument Type: {}", function.var_arg)?;
+        writeln!(f, "- Slot Count: {}", function.slot_count)?;
+
+        writeln!(f, "Instructions:")?;
+        for inst in &function.instructions {
+            if inst.mode == HSOpCode::Unknown {
+                writeln!(f, "- Unknown({:#010x})", inst.raw)?;
+                continue;
+            }
+            write!(f, "- {}:", inst.mode)?;
+            for arg in &inst.args {
+                write!(f, " {}({})", arg.mode, arg.value)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(f, "Constants:")?;
+        for constant in &function.constants {
+            Self::fmt_constant(f, constant)?;
+        }
+
+        if function.has_debug_info {
+            let debug_info = &function.debug_info;
+            writeln!(f, "Debug Info:")?;
+            writeln!(f, "- Line Begin: {}", debug_info.line_begin)?;
+            writeln!(f, "- Line End: {}", debug_info.line_end)?;
+            writeln!(f, "- Path: {:?}", debug_info.path)?;
+            writeln!(f, "- Function Name: {:?}", debug_info.function_name)?;
+            write!(f, "- Lines:")?;
+            for line in &debug_info.lines {
+                write!(f, " {line}")?;
+            }
+            writeln!(f)?;
+            writeln!(f, "- Locals:")?;
+            for local in &debug_info.locals {
+                writeln!(
+                    f,
+                    "   - {:?}: {} {}",
+                    local.local_name, local.start, local.end
+                )?;
+            }
+            writeln!(f, "- UpValues:")?;
+            for up_value in &debug_info.up_values {
+                writeln!(f, "   - {up_value:?}")?;
+            }
+        }
+
+        for child in &function.child_functions {
+            Self::fmt_function(f, child)?;
+        }
+        writeln!(f, "[End Function]")
+    }
+
+    fn fmt_constant(f: &mut Formatter<'_>, constant: &HSConstant) -> std::fmt::Result {
+        write!(f, "- {:?}", constant.type_)?;
+        match &constant.value {
+            Some(HSValue::Boolean(b)) => writeln!(f, ": {b}"),
+            Some(HSValue::Number(n)) => writeln!(f, ": {n:?}"),
+            Some(HSValue::String(s)) => writeln!(f, ": {s:?}"),
+            Some(HSValue::LightUserData(n) | HSValue::Ui64(n)) => writeln!(f, ": {n}"),
+            Some(HSValue::Nil) | None => writeln!(f),
+        }
+    }
+
+    fn fmt_structure(f: &mut Formatter<'_>, structure: &HSStructPrototype) -> std::fmt::Result {
+        writeln!(f, "[Structure]")?;
+        writeln!(f, "- Name: {:?}", structure.name)?;
+        writeln!(f, "- Id: {}", structure.id)?;
+        writeln!(f, "- Has Meta: {}", structure.has_meta)?;
+        writeln!(f, "- Has Proxy: {}", structure.has_proxy)?;
+        write!(f, "- Inherited From:")?;
+        for name in &structure.inherited_structs {
+            write!(f, " {name:?}")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "- Slots:")?;
+        for slot in &structure.slots {
+            writeln!(
+                f,
+                "   - {:?} {:?}: {} {} {}",
+                slot.type_, slot.name, slot.struct_id, slot.reserved, slot.position
+            )?;
+        }
+        writeln!(f, "[End Structure]")
+    }
+}
+
+impl Display for HSListing<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let header = &self.file.header;
+        writeln!(f, "[Header]")?;
+        writeln!(f, "- Is Little Endian: {}", header.is_little_endian)?;
+        writeln!(f, "- Integer Size: {}", header.int_size)?;
+        writeln!(f, "- Type Size: {}", header.t_size)?;
+        writeln!(f, "- Instruction Size: {}", header.instruction_size)?;
+        writeln!(f, "- Number Size: {}", header.number_size)?;
+        writeln!(f, "- Is Using Integer: {}", header.is_integer)?;
+        write!(f, "- Extensions: [")?;
+        for (i, (name, _)) in header.features.iter_names().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}")?;
+        }
+        writeln!(f, "]")?;
+        writeln!(f, "- Shared: {}", header.shared)?;
+        writeln!(f, "- Opcode Profile: {}", self.file.profile.name)?;
+        writeln!(
+            f,
+            "- Structure Inheritance: {}",
+            self.file.enable_inheritance
+        )?;
+        writeln!(f)?;
+
+        writeln!(f, "[Enums]")?;
+        for item in &self.file.enums {
+            writeln!(f, "- {}: {} {}", item.name, item.value, item.length)?;
+        }
+        writeln!(f)?;
+
+        Self::fmt_function(f, &self.file.main_function)?;
+
+        for structure in &self.file.structs {
+            writeln!(f)?;
+            Self::fmt_structure(f, structure)?;
+        }
+        Ok(())
+    }
+}
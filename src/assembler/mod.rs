@@ -0,0 +1,23 @@
+//! Module containing the assembler, which turns editable listings back into `HavokScript` bytecode.
+
+pub mod listing;
+pub mod parser;
+
+use crate::{common::errors::HkscError, loader::hs_profile::HSOpcodeProfile};
+use parser::HSListingParser;
+
+/// Assembles a listing written by `HSListing` into a `HavokScript` 5.1 file.
+pub fn assemble(source: &str) -> Result<Vec<u8>, HkscError> {
+    let file = HSListingParser::parse(source)?;
+    file.to_bytes()
+}
+
+/// Assembles a listing like `assemble`, numbering opcodes with `profile` unless the listing
+/// names a preset.
+pub fn assemble_with_profile(
+    source: &str,
+    profile: &HSOpcodeProfile,
+) -> Result<Vec<u8>, HkscError> {
+    let file = HSListingParser::parse_with_profile(source, Some(profile))?;
+    file.to_bytes()
+}
@@ -0,0 +1,691 @@
+//! Parser for the listing format written by `HSListing`.
+//!
+//! The parser works line by line, keeping track of the section it is in.
+//! Blank lines and lines starting with `;` are ignored.
+
+use crate::{
+    common::errors::HkscError,
+    loader::{
+        hs::HavokScriptFile,
+        hs_constant::{HSConstant, HSValue},
+        hs_debug::HSFunctionDebugInfoLocals,
+        hs_encoding::HSInstructionEncoding,
+        hs_enums::HSEnum,
+        hs_function::{HSFunction, HSVarArg},
+        hs_header::HSFeatures,
+        hs_instruction::{HSInstruction, HSInstructionArg},
+        hs_opcodes::{
+            HSMode, HSOpArgMode, HSOpArgModeA, HSOpArgModeBC, HSOpCode, HSOpMode, HSType, OP_TABLE,
+        },
+        hs_profile::HSOpcodeProfile,
+        hs_structure::{HSStructPrototype, HSStructSlot},
+    },
+};
+
+use std::{ops::RangeInclusive, str::FromStr};
+
+/// Section of the listing that is currently being parsed.
+#[derive(PartialEq, Eq)]
+enum Section {
+    None,
+    Header,
+    Enums,
+    Function,
+    Instructions,
+    Constants,
+    DebugInfo,
+    Locals,
+    UpValues,
+    Children,
+    Structure,
+    Slots,
+}
+
+/// Line based parser that builds a `HavokScriptFile` out of a listing.
+pub struct HSListingParser {
+    /// The file being built.
+    file: HavokScriptFile,
+    /// Section the current line belongs to.
+    section: Section,
+    /// Functions that have been opened but not closed yet, innermost last.
+    functions: Vec<HSFunction>,
+    /// Whether the main function has been closed.
+    has_main_function: bool,
+    /// Structure that has been opened but not closed yet.
+    structure: Option<HSStructPrototype>,
+    /// Current line number, starting at 1.
+    line: usize,
+    /// Profile given for listings that name a profile which isn't a preset.
+    profile: Option<HSOpcodeProfile>,
+}
+
+impl HSListingParser {
+    /// Parses a listing into a file.
+    pub fn parse(source: &str) -> Result<HavokScriptFile, HkscError> {
+        Self::parse_with_profile(source, None)
+    }
+
+    /// Parses a listing into a file, numbering opcodes with `profile` when the listing names it
+    /// or doesn't name a profile. Listings naming a preset use that preset.
+    pub fn parse_with_profile(
+        source: &str,
+        profile: Option<&HSOpcodeProfile>,
+    ) -> Result<HavokScriptFile, HkscError> {
+        let mut file = HavokScriptFile::default();
+        if let Some(profile) = profile {
+            file.profile = profile.clone();
+        }
+        let mut parser = Self {
+            file,
+            section: Section::None,
+            functions: Vec::new(),
+            has_main_function: false,
+            structure: None,
+            line: 0,
+            profile: profile.cloned(),
+        };
+        for line in source.lines() {
+            parser.line += 1;
+            parser.parse_line(line.trim())?;
+        }
+        parser.finish()
+    }
+
+    fn error(&self, message: impl Into<String>) -> HkscError {
+        HkscError::AssemblyError {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn finish(mut self) -> Result<HavokScriptFile, HkscError> {
+        if !self.functions.is_empty() {
+            return Err(self.error("missing [End Function]"));
+        }
+        if self.structure.is_some() {
+            return Err(self.error("missing [End Structure]"));
+        }
+        if !self.has_main_function {
+            return Err(self.error("missing main function"));
+        }
+        self.file.main_function.assign_ids("0", 0);
+        self.file.resolve_inheritance();
+        let instruction_size = self.file.header.instruction_size;
+        if HSInstructionEncoding::for_size(instruction_size).is_err() {
+            return Err(self.error(format!(
+                "Instruction Size {instruction_size} is not supported"
+            )));
+        }
+        let header = &mut self.file.header;
+        header.magic = 1_635_077_147;
+        header.version = 0x51;
+        header.fmt = 14;
+        header.enum_count = u32::try_from(self.file.enums.len())?;
+        Ok(self.file)
+    }
+
+    fn parse_line(&mut self, line: &str) -> Result<(), HkscError> {
+        if line.is_empty() || line.starts_with(';') {
+            return Ok(());
+        }
+        match line {
+            "[Header]" => self.enter_top_level(Section::Header),
+            "[Enums]" => self.enter_top_level(Section::Enums),
+            "[Function]" => self.open_function(),
+            "[End Function]" => self.close_function(),
+            "[Structure]" => {
+                self.enter_top_level(Section::Structure)?;
+                self.structure = Some(HSStructPrototype::default());
+                Ok(())
+            }
+            "[End Structure]" => {
+                let structure = self
+                    .structure
+                    .take()
+                    .ok_or_else(|| self.error("[End Structure] without [Structure]"))?;
+                self.file.structs.push(structure);
+                self.section = Section::None;
+                Ok(())
+            }
+            "Instructions:" => self.enter_function_section(Section::Instructions),
+            "Constants:" => self.enter_function_section(Section::Constants),
+            "Debug Info:" => {
+                self.enter_function_section(Section::DebugInfo)?;
+                self.current_function()?.has_debug_info = true;
+                Ok(())
+            }
+            _ => match line.strip_prefix('-') {
+                Some(item) => self.parse_item(item.trim()),
+                None => Err(self.error(format!("unexpected line `{line}`"))),
+            },
+        }
+    }
+
+    fn enter_top_level(&mut self, section: Section) -> Result<(), HkscError> {
+        if !self.functions.is_empty() {
+            return Err(self.error("missing [End Function]"));
+        }
+        if self.structure.is_some() {
+            return Err(self.error("missing [End Structure]"));
+        }
+        self.section = section;
+        Ok(())
+    }
+
+    fn enter_function_section(&mut self, section: Section) -> Result<(), HkscError> {
+        if self.functions.is_empty() || self.section == Section::Children {
+            return Err(self.error("section outside of a function"));
+        }
+        self.section = section;
+        Ok(())
+    }
+
+    fn current_function(&mut self) -> Result<&mut HSFunction, HkscError> {
+        let line = self.line;
+        self.functions.last_mut().ok_or(HkscError::AssemblyError {
+            line,
+            message: "item outside of a function".to_string(),
+        })
+    }
+
+    fn open_function(&mut self) -> Result<(), HkscError> {
+        if self.structure.is_some() {
+            return Err(self.error("missing [End Structure]"));
+        }
+        if self.functions.is_empty() && self.has_main_function {
+            return Err(self.error("only one main function is allowed"));
+        }
+        self.functions.push(HSFunction::default());
+        self.section = Section::Function;
+        Ok(())
+    }
+
+    fn close_function(&mut self) -> Result<(), HkscError> {
+        let mut function = self
+            .functions
+            .pop()
+            .ok_or_else(|| self.error("[End Function] without [Function]"))?;
+        function.instruction_count = u64::try_from(function.instructions.len())?;
+        function.constant_count = u32::try_from(function.constants.len())?;
+        function.function_count = u32::try_from(function.child_functions.len())?;
+        let debug_info = &mut function.debug_info;
+        debug_info.line_count = u32::try_from(debug_info.lines.len())?;
+        debug_info.locals_count = u32::try_from(debug_info.locals.len())?;
+        debug_info.up_value_count = u32::try_from(debug_info.up_values.len())?;
+
+        if let Some(parent) = self.functions.last_mut() {
+            parent.child_functions.push(function);
+            self.section = Section::Children;
+        } else {
+            self.file.main_function = function;
+            self.has_main_function = true;
+            self.section = Section::None;
+        }
+        Ok(())
+    }
+
+    fn parse_item(&mut self, item: &str) -> Result<(), HkscError> {
+        match self.section {
+            Section::None | Section::Children => Err(self.error("item outside of a section")),
+            Section::Header => self.parse_header_item(item),
+            Section::Enums => {
+                let (name, fields) = item
+                    .rsplit_once(':')
+                    .ok_or_else(|| self.error("expected `NAME: VALUE LENGTH`"))?;
+                let mut fields = fields.split_whitespace();
+                let value = self.parse_value(fields.next().unwrap_or_default())?;
+                let mut item = HSEnum::new(name.to_string(), value);
+                // Listings without a length keep the length of the name.
+                if let Some(length) = fields.next() {
+                    item.length = self.parse_value(length)?;
+                }
+                if fields.next().is_some() {
+                    return Err(self.error("expected `NAME: VALUE LENGTH`"));
+                }
+                self.file.enums.push(item);
+                Ok(())
+            }
+            Section::Function => self.parse_function_item(item),
+            Section::Instructions => {
+                let instruction = self.parse_instruction(item)?;
+                self.current_function()?.instructions.push(instruction);
+                Ok(())
+            }
+            Section::Constants => {
+                let constant = self.parse_constant(item)?;
+                self.current_function()?.constants.push(constant);
+                Ok(())
+            }
+            Section::Locals if item.starts_with('"') => {
+                let (name, rest) = self.parse_string(item)?;
+                let range = self.strip_colon(rest)?;
+                let [start, end] = self.parse_values(range)?;
+                let local = HSFunctionDebugInfoLocals::new(name, start, end);
+                self.current_function()?.debug_info.locals.push(local);
+                Ok(())
+            }
+            Section::UpValues if item.starts_with('"') => {
+                let (name, rest) = self.parse_string(item)?;
+                self.expect_end(rest)?;
+                self.current_function()?.debug_info.up_values.push(name);
+                Ok(())
+            }
+            Section::DebugInfo | Section::Locals | Section::UpValues => {
+                self.section = Section::DebugInfo;
+                self.parse_debug_info_item(item)
+            }
+            Section::Structure => self.parse_structure_item(item),
+            Section::Slots => {
+                let slot = self.parse_slot(item)?;
+                self.structure_mut()?.slots.push(slot);
+                Ok(())
+            }
+        }
+    }
+
+    fn split_key<'a>(&self, item: &'a str) -> Result<(&'a str, &'a str), HkscError> {
+        item.split_once(':')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| self.error("expected `KEY: VALUE`"))
+    }
+
+    fn parse_header_item(&mut self, item: &str) -> Result<(), HkscError> {
+        let (key, value) = self.split_key(item)?;
+        match key {
+            "Is Little Endian" => self.file.header.is_little_endian = self.parse_value(value)?,
+            "Integer Size" => self.file.header.int_size = self.parse_value(value)?,
+            "Type Size" => self.file.header.t_size = self.parse_value(value)?,
+            "Instruction Size" => self.file.header.instruction_size = self.parse_value(value)?,
+            "Number Size" => self.file.header.number_size = self.parse_value(value)?,
+            "Is Using Integer" => self.file.header.is_integer = self.parse_value(value)?,
+            "Extensions" => self.file.header.features = self.parse_features(value)?,
+            "Shared" => self.file.header.shared = self.parse_value(value)?,
+            "Opcode Profile" => {
+                self.file.profile = match (&self.profile, HSOpcodeProfile::preset(value)) {
+                    (Some(profile), _) if profile.name == value => profile.clone(),
+                    (_, Some(preset)) => preset,
+                    _ => {
+                        return Err(self.error(format!(
+                            "the listing uses the opcode profile `{value}`, which must be given to assemble it"
+                        )));
+                    }
+                };
+            }
+            "Structure Inheritance" => {
+                self.file.enable_inheritance = self.parse_value(value)?;
+            }
+            _ => return Err(self.error(format!("unknown header field `{key}`"))),
+        }
+        Ok(())
+    }
+
+    fn parse_features(&self, value: &str) -> Result<HSFeatures, HkscError> {
+        let names = value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .ok_or_else(|| self.error("expected `[FEATURE, ...]`"))?;
+        let mut features = HSFeatures::empty();
+        for name in names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            features |= HSFeatures::from_name(name)
+                .ok_or_else(|| self.error(format!("unknown extension `{name}`")))?;
+        }
+        Ok(features)
+    }
+
+    fn parse_function_item(&mut self, item: &str) -> Result<(), HkscError> {
+        let (key, value) = self.split_key(item)?;
+        match key {
+            "UpValue Count" => self.current_function()?.up_value_count = self.parse_value(value)?,
+            "Parameter Count" => self.current_function()?.param_count = self.parse_value(value)?,
+            "Variadic Argument Type" => {
+                let mut var_arg = HSVarArg::NONE;
+                for name in value.split('|').map(str::trim) {
+                    var_arg |= HSVarArg::from_name(name)
+                        .ok_or_else(|| self.error(format!("unknown variadic flag `{name}`")))?;
+                }
+                self.current_function()?.var_arg = var_arg;
+            }
+            "Slot Count" => self.current_function()?.slot_count = self.parse_value(value)?,
+            _ => return Err(self.error(format!("unknown function field `{key}`"))),
+        }
+        Ok(())
+    }
+
+    fn parse_debug_info_item(&mut self, item: &str) -> Result<(), HkscError> {
+        let (key, value) = self.split_key(item)?;
+        match key {
+            "Line Begin" => {
+                self.current_function()?.debug_info.line_begin = self.parse_value(value)?;
+            }
+            "Line End" => self.current_function()?.debug_info.line_end = self.parse_value(value)?,
+            "Path" => {
+                let (path, rest) = self.parse_string(value)?;
+                self.expect_end(rest)?;
+                self.current_function()?.debug_info.path = path;
+            }
+            "Function Name" => {
+                let (name, rest) = self.parse_string(value)?;
+                self.expect_end(rest)?;
+                self.current_function()?.debug_info.function_name = name;
+            }
+            "Lines" => {
+                let lines = value
+                    .split_whitespace()
+                    .map(|line| self.parse_value(line))
+                    .collect::<Result<Vec<u32>, HkscError>>()?;
+                self.current_function()?.debug_info.lines = lines;
+            }
+            "Locals" => {
+                self.expect_end(value)?;
+                self.section = Section::Locals;
+            }
+            "UpValues" => {
+                self.expect_end(value)?;
+                self.section = Section::UpValues;
+            }
+            _ => return Err(self.error(format!("unknown debug info field `{key}`"))),
+        }
+        Ok(())
+    }
+
+    fn structure_mut(&mut self) -> Result<&mut HSStructPrototype, HkscError> {
+        let line = self.line;
+        self.structure.as_mut().ok_or(HkscError::AssemblyError {
+            line,
+            message: "item outside of a structure".to_string(),
+        })
+    }
+
+    fn parse_structure_item(&mut self, item: &str) -> Result<(), HkscError> {
+        let (key, value) = self.split_key(item)?;
+        match key {
+            "Name" => {
+                let (name, rest) = self.parse_string(value)?;
+                self.expect_end(rest)?;
+                self.structure_mut()?.name = name;
+            }
+            "Id" => self.structure_mut()?.id = self.parse_value(value)?,
+            "Has Meta" => self.structure_mut()?.has_meta = self.parse_value(value)?,
+            "Has Proxy" => self.structure_mut()?.has_proxy = self.parse_value(value)?,
+            "Inherited From" => {
+                let mut rest = value;
+                let mut names = Vec::new();
+                while !rest.is_empty() {
+                    let (name, remainder) = self.parse_string(rest)?;
+                    names.push(name);
+                    rest = remainder.trim_start();
+                }
+                let structure = self.structure_mut()?;
+                structure.inherited_count = u32::try_from(names.len())?;
+                structure.inherited_structs = names;
+            }
+            "Slots" => {
+                self.expect_end(value)?;
+                self.section = Section::Slots;
+            }
+            _ => return Err(self.error(format!("unknown structure field `{key}`"))),
+        }
+        Ok(())
+    }
+
+    fn parse_slot(&mut self, item: &str) -> Result<HSStructSlot, HkscError> {
+        let (type_name, rest) = item
+            .split_once(' ')
+            .ok_or_else(|| self.error("expected `TYPE \"NAME\": STRUCT_ID RESERVED POSITION`"))?;
+        let (name, rest) = self.parse_string(rest.trim_start())?;
+        let [struct_id, reserved, position] = self.parse_values(self.strip_colon(rest)?)?;
+        let structure = self.structure_mut()?;
+        structure.slot_count += 1;
+        Ok(HSStructSlot {
+            name,
+            struct_id,
+            type_: self.parse_type(type_name)?,
+            reserved: u32::try_from(reserved)?,
+            position,
+        })
+    }
+
+    fn parse_type(&self, name: &str) -> Result<HSType, HkscError> {
+        (0..=u8::MAX)
+            .map_while(|i| HSType::try_from(i).ok())
+            .find(|type_| format!("{type_:?}") == name)
+            .ok_or_else(|| self.error(format!("unknown type `{name}`")))
+    }
+
+    fn parse_constant(&self, item: &str) -> Result<HSConstant, HkscError> {
+        let (type_name, value) = match item.split_once(':') {
+            Some((type_name, value)) => (type_name.trim(), Some(value.trim())),
+            None => (item, None),
+        };
+        let type_ = self.parse_type(type_name)?;
+        let value = match (type_, value) {
+            (HSType::TNIL, None) => HSValue::Nil,
+            (HSType::TBOOLEAN, Some(value)) => HSValue::Boolean(self.parse_value(value)?),
+            (HSType::TLIGHTUSERDATA, Some(value)) => {
+                HSValue::LightUserData(self.parse_value(value)?)
+            }
+            (HSType::TNUMBER, Some(value)) => HSValue::Number(self.parse_value(value)?),
+            (HSType::TSTRING, Some(value)) => {
+                let (string, rest) = self.parse_string(value)?;
+                self.expect_end(rest)?;
+                HSValue::String(string)
+            }
+            (HSType::TUI64, Some(value)) => HSValue::Ui64(self.parse_value(value)?),
+            _ => return Err(self.error(format!("invalid {type_name} constant"))),
+        };
+        Ok(HSConstant {
+            type_,
+            value: Some(value),
+        })
+    }
+
+    fn parse_instruction(&self, item: &str) -> Result<HSInstruction, HkscError> {
+        if let Some(raw) = item
+            .strip_prefix("Unknown(")
+            .and_then(|item| item.strip_suffix(')'))
+        {
+            return self.parse_unknown_instruction(raw);
+        }
+        let (name, operands) = item.split_once(':').unwrap_or((item, ""));
+        let name = name.trim();
+        let mode = (0..=u8::MAX)
+            .map_while(|i| HSOpCode::try_from(i).ok())
+            .take(OP_TABLE.len())
+            .find(|op_code| op_code.to_string() == name)
+            .ok_or_else(|| self.error(format!("unknown opcode `{name}`")))?;
+        let profile = &self.file.profile;
+        let (number, modes) = profile
+            .number(mode)
+            .and_then(|number| Some((number, profile.mode(number)?)))
+            .ok_or_else(|| {
+                self.error(format!(
+                    "{name} is not mapped by the opcode profile `{}`",
+                    profile.name
+                ))
+            })?;
+        // The extension bit of 'B' is the lowest opcode bit, so only odd opcode numbers, those
+        // of the `Bk` variants, can take a constant there.
+        let fields = operand_fields(modes, number & 1 == 1);
+
+        let operands = operands.split_whitespace().collect::<Vec<_>>();
+        if operands.len() != fields.len() {
+            return Err(self.error(format!(
+                "{name} expects {} operands, found {}",
+                fields.len(),
+                operands.len()
+            )));
+        }
+
+        let mut args = Vec::with_capacity(fields.len());
+        for (operand, (modes, range)) in operands.iter().zip(fields) {
+            let (mode_name, value) = operand
+                .strip_suffix(')')
+                .and_then(|operand| operand.split_once('('))
+                .ok_or_else(|| self.error(format!("expected `MODE(VALUE)`, found `{operand}`")))?;
+            let arg_mode = modes
+                .iter()
+                .find(|mode| mode.to_string() == mode_name)
+                .ok_or_else(|| self.error(format!("unexpected {mode_name} operand for {name}")))?;
+            let value = self.parse_value(value)?;
+            if !range.contains(&value) {
+                return Err(self.error(format!("operand `{operand}` is out of range for {name}")));
+            }
+            args.push(HSInstructionArg {
+                mode: arg_mode.clone(),
+                value,
+            });
+        }
+        // Starting from the number checked above keeps it when the profile maps several numbers
+        // to the opcode.
+        let mut instruction = HSInstruction {
+            mode,
+            args,
+            raw: HSInstructionEncoding::STANDARD.opcode.set(0, number.into()),
+        };
+        instruction.raw = instruction.encode_with(profile, &HSInstructionEncoding::STANDARD)?;
+        Ok(instruction)
+    }
+
+    /// Parses a quoted string with Rust style escapes, returning it and the remainder of the input.
+    fn parse_string<'a>(&self, input: &'a str) -> Result<(String, &'a str), HkscError> {
+        let mut chars = input
+            .strip_prefix('"')
+            .ok_or_else(|| self.error("expected a quoted string"))?
+            .char_indices();
+        let mut string = String::new();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((string, &input[i + 2..])),
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('0') => '\0',
+                        Some(c @ ('\\' | '"' | '\'')) => c,
+                        Some('u') => {
+                            let mut code = String::new();
+                            if chars.next().map(|(_, c)| c) != Some('{') {
+                                return Err(self.error("expected `{` in unicode escape"));
+                            }
+                            for (_, c) in chars.by_ref() {
+                                if c == '}' {
+                                    break;
+                                }
+                                code.push(c);
+                            }
+                            u32::from_str_radix(&code, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    string.push(escaped);
+                }
+                _ => string.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn strip_colon<'a>(&self, input: &'a str) -> Result<&'a str, HkscError> {
+        input
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(|| self.error("expected `:`"))
+    }
+
+    fn expect_end(&self, input: &str) -> Result<(), HkscError> {
+        if input.trim().is_empty() {
+            Ok(())
+        } else {
+            Err(self.error(format!("unexpected `{}`", input.trim())))
+        }
+    }
+
+    /// Parses the instruction word of an `Unknown(0x...)` instruction, which is kept as-is.
+    fn parse_unknown_instruction(&self, raw: &str) -> Result<HSInstruction, HkscError> {
+        let raw = raw
+            .strip_prefix("0x")
+            .and_then(|raw| u64::from_str_radix(raw, 16).ok())
+            .ok_or_else(|| self.error(format!("invalid instruction word `{raw}`")))?;
+        let opcode = HSInstructionEncoding::STANDARD.opcode.get(raw);
+        if u8::try_from(opcode).is_ok_and(|opcode| self.file.profile.mode(opcode).is_some()) {
+            return Err(self.error(format!("instruction word `{raw:#010x}` has a known opcode")));
+        }
+        Ok(HSInstruction {
+            mode: HSOpCode::Unknown,
+            args: Vec::new(),
+            raw,
+        })
+    }
+
+    fn parse_value<T: FromStr>(&self, value: &str) -> Result<T, HkscError> {
+        value
+            .parse()
+            .map_err(|_| self.error(format!("invalid value `{value}`")))
+    }
+
+    fn parse_values<T: FromStr, const N: usize>(&self, input: &str) -> Result<[T; N], HkscError> {
+        let values = input
+            .split_whitespace()
+            .map(|value| self.parse_value(value))
+            .collect::<Result<Vec<T>, HkscError>>()?;
+        values
+            .try_into()
+            .map_err(|_| self.error(format!("expected {N} values")))
+    }
+}
+
+/// Returns the accepted argument modes and value range of each operand of an instruction,
+/// mirroring how the decoder splits up instruction words.
+///
+/// `b_constants` tells whether a 'B' argument that is either a register or a constant may be a
+/// constant, as its extension bit is shared with the opcode.
+fn operand_fields(
+    modes: &HSMode,
+    b_constants: bool,
+) -> Vec<(Vec<HSOpArgMode>, RangeInclusive<i32>)> {
+    let abc_field = |mode: &HSOpArgModeBC| match mode {
+        HSOpArgModeBC::NUMBER => (vec![HSOpArgMode::NUMBER], 0..=0xFF),
+        HSOpArgModeBC::OFFSET => (vec![HSOpArgMode::NUMBER], 0..=0x1FF),
+        HSOpArgModeBC::REG => (vec![HSOpArgMode::REG], 0..=0xFF),
+        HSOpArgModeBC::REGCONST => (vec![HSOpArgMode::REG, HSOpArgMode::CONST], 0..=0xFF),
+        HSOpArgModeBC::CONST | HSOpArgModeBC::UNUSED => (vec![HSOpArgMode::CONST], 0..=0xFF),
+    };
+
+    let mut fields = Vec::with_capacity(3);
+    fields.push(match modes.op_mode_a {
+        HSOpArgModeA::UNUSED => (vec![HSOpArgMode::NUMBER], 0..=0xFF),
+        HSOpArgModeA::REG => (vec![HSOpArgMode::REG], 0..=0xFF),
+    });
+    if modes.op_mode_b != HSOpArgModeBC::UNUSED {
+        fields.push(match modes.op_mode {
+            HSOpMode::ABC if modes.op_mode_b == HSOpArgModeBC::REGCONST && !b_constants => {
+                (vec![HSOpArgMode::REG], 0..=0xFF)
+            }
+            HSOpMode::ABC => abc_field(&modes.op_mode_b),
+            HSOpMode::ABX | HSOpMode::ASBX => {
+                let mode = match modes.op_mode_b {
+                    HSOpArgModeBC::CONST => HSOpArgMode::CONST,
+                    _ => HSOpArgMode::NUMBER,
+                };
+                let range = if modes.op_mode == HSOpMode::ASBX {
+                    -0xFFFF..=0x10000
+                } else {
+                    0..=0x1FFFF
+                };
+                (vec![mode], range)
+            }
+        });
+    }
+    if modes.op_mode == HSOpMode::ABC && modes.op_mode_c != HSOpArgModeBC::UNUSED {
+        fields.push(abc_field(&modes.op_mode_c));
+    }
+    fields
+}
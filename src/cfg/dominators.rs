@@ -0,0 +1,87 @@
+//! Dominator computation for control-flow graphs.
+//!
+//! Uses the iterative algorithm from "A Simple, Fast Dominance Algorithm" by Cooper, Harvey and
+//! Kennedy, which converges in a couple of passes on the reducible graphs Lua compilers produce.
+
+use super::HSControlFlowGraph;
+
+/// Computes the immediate dominator of every block.
+/// The entry block and blocks that can't be reached from it have no immediate dominator.
+#[must_use]
+pub fn compute_dominators(graph: &HSControlFlowGraph) -> Vec<Option<usize>> {
+    let count = graph.blocks.len();
+    if count == 0 {
+        return Vec::new();
+    }
+
+    let order = reverse_postorder(graph);
+    let mut rank = vec![usize::MAX; count];
+    for (index, block) in order.iter().enumerate() {
+        rank[*block] = index;
+    }
+
+    let mut dominators: Vec<Option<usize>> = vec![None; count];
+    dominators[0] = Some(0);
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in order.iter().skip(1) {
+            let mut new_dominator = None;
+            for edge in graph.predecessors(*block) {
+                if dominators[edge.from].is_none() {
+                    continue;
+                }
+                new_dominator = Some(match new_dominator {
+                    None => edge.from,
+                    Some(current) => intersect(&dominators, &rank, edge.from, current),
+                });
+            }
+            if new_dominator.is_some() && dominators[*block] != new_dominator {
+                dominators[*block] = new_dominator;
+                changed = true;
+            }
+        }
+    }
+
+    dominators[0] = None;
+    dominators
+}
+
+/// Finds the closest common dominator of two blocks.
+fn intersect(dominators: &[Option<usize>], rank: &[usize], mut a: usize, mut b: usize) -> usize {
+    while a != b {
+        while rank[a] > rank[b] {
+            a = dominators[a].unwrap_or(0);
+        }
+        while rank[b] > rank[a] {
+            b = dominators[b].unwrap_or(0);
+        }
+    }
+    a
+}
+
+/// Orders the blocks reachable from the entry so that each block comes before its successors,
+/// ignoring back edges.
+fn reverse_postorder(graph: &HSControlFlowGraph) -> Vec<usize> {
+    let mut visited = vec![false; graph.blocks.len()];
+    let mut postorder = Vec::new();
+    // Explicit stack of (block, next successor to visit), as functions can have thousands of blocks.
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+    while let Some((block, next)) = stack.last_mut() {
+        let block = *block;
+        if let Some(edge) = graph.blocks[block].successors.get(*next) {
+            *next += 1;
+            let to = graph.edges[*edge].to;
+            if !visited[to] {
+                visited[to] = true;
+                stack.push((to, 0));
+            }
+        } else {
+            postorder.push(block);
+            stack.pop();
+        }
+    }
+    postorder.reverse();
+    postorder
+}
@@ -0,0 +1,235 @@
+//! Writes the control-flow graphs of a `HavokScriptFile` in the Graphviz DOT format.
+//!
+//! Every function becomes its own `digraph`, or a cluster nested in its parent's cluster when
+//! clusters are enabled. Nodes are basic blocks listing their disassembled instructions, and the
+//! edges leaving a conditional block are labelled with the result of the comparison or test that
+//! leads along them, which depends on the expected result held in operand A of comparisons and C
+//! of tests.
+
+use super::{HSControlFlowGraph, HSEdge, HSEdgeKind};
+use crate::loader::{
+    hs::HavokScriptFile,
+    hs_constant::HSValue,
+    hs_function::HSFunction,
+    hs_instruction::{HSInstruction, HSInstructionArg},
+    hs_opcodes::{HSOpArgMode, HSOpCode},
+};
+
+use std::fmt::{Display, Formatter, Write};
+
+/// Graphviz view of the control flow of a `HavokScriptFile`.
+pub struct HSDotGraph<'a> {
+    /// The function to graph, along with its children.
+    function: &'a HSFunction,
+    /// Whether child functions are nested as clusters inside their parent's graph.
+    clusters: bool,
+}
+
+impl<'a> HSDotGraph<'a> {
+    #[must_use]
+    pub fn new(file: &'a HavokScriptFile, clusters: bool) -> Self {
+        Self::for_function(&file.main_function, clusters)
+    }
+
+    /// Graphs a single function and its children.
+    #[must_use]
+    pub fn for_function(function: &'a HSFunction, clusters: bool) -> Self {
+        Self { function, clusters }
+    }
+
+    /// Returns the name shown for a function, from debug info when available.
+    fn function_name(function: &HSFunction, id: &str) -> String {
+        if function.has_debug_info && !function.debug_info.function_name.is_empty() {
+            function.debug_info.function_name.clone()
+        } else {
+            match id {
+                "0" => "main".to_string(),
+                _ => format!("function {}", id.replace('_', "/")),
+            }
+        }
+    }
+
+    fn fmt_graph(f: &mut Formatter<'_>, function: &HSFunction, id: &str) -> std::fmt::Result {
+        writeln!(f, "digraph \"f{id}\" {{")?;
+        writeln!(
+            f,
+            "    label=\"{}\";",
+            escape(&Self::function_name(function, id))
+        )?;
+        writeln!(f, "    node [shape=box, fontname=\"monospace\"];")?;
+        Self::fmt_body(f, function, id, 1)?;
+        writeln!(f, "}}")?;
+        for (index, child) in function.child_functions.iter().enumerate() {
+            writeln!(f)?;
+            Self::fmt_graph(f, child, &format!("{id}_{index}"))?;
+        }
+        Ok(())
+    }
+
+    fn fmt_cluster(
+        f: &mut Formatter<'_>,
+        function: &HSFunction,
+        id: &str,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let indent = "    ".repeat(depth);
+        writeln!(f, "{indent}subgraph \"cluster_f{id}\" {{")?;
+        writeln!(
+            f,
+            "{indent}    label=\"{}\";",
+            escape(&Self::function_name(function, id))
+        )?;
+        Self::fmt_body(f, function, id, depth + 1)?;
+        for (index, child) in function.child_functions.iter().enumerate() {
+            Self::fmt_cluster(f, child, &format!("{id}_{index}"), depth + 1)?;
+        }
+        writeln!(f, "{indent}}}")?;
+
+        // Link closures to the entry of the function they create.
+        let graph = HSControlFlowGraph::new(function);
+        for (pc, instruction) in function.instructions.iter().enumerate() {
+            if instruction.mode != HSOpCode::Closure {
+                continue;
+            }
+            let child = instruction
+                .args
+                .get(1)
+                .and_then(|arg| usize::try_from(arg.value).ok());
+            if let (Some(block), Some(child)) = (graph.block_at(pc), child)
+                && function
+                    .child_functions
+                    .get(child)
+                    .is_some_and(|child| !child.instructions.is_empty())
+            {
+                writeln!(
+                    f,
+                    "{indent}\"f{id}_b{block}\" -> \"f{id}_{child}_b0\" [style=dotted, label=\"closure\"];"
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the blocks and edges of a function.
+    fn fmt_body(
+        f: &mut Formatter<'_>,
+        function: &HSFunction,
+        id: &str,
+        depth: usize,
+    ) -> std::fmt::Result {
+        let indent = "    ".repeat(depth);
+        let graph = HSControlFlowGraph::new(function);
+        for (index, block) in graph.blocks.iter().enumerate() {
+            let mut label = format!("B{index}\\l");
+            for pc in block.start..block.end {
+                let instruction = &function.instructions[pc];
+                label.push_str(&escape(&format!(
+                    "{pc}: {}",
+                    Self::instruction(function, instruction)
+                )));
+                label.push_str("\\l");
+            }
+            writeln!(f, "{indent}\"f{id}_b{index}\" [label=\"{label}\"];")?;
+        }
+        for edge in &graph.edges {
+            write!(
+                f,
+                "{indent}\"f{id}_b{}\" -> \"f{id}_b{}\"",
+                edge.from, edge.to
+            )?;
+            match Self::edge_label(function, &graph, edge) {
+                Some("loop") => writeln!(f, " [label=\"loop\", style=dashed];")?,
+                Some(label) => writeln!(f, " [label=\"{label}\"];")?,
+                None => writeln!(f, ";")?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Formats an instruction like the disassembly, with constants resolved.
+    fn instruction(function: &HSFunction, instruction: &HSInstruction) -> String {
+        if instruction.mode == HSOpCode::Unknown {
+            return format!("Unknown({:#010x})", instruction.raw);
+        }
+        let mut text = instruction.mode.to_string();
+        for arg in &instruction.args {
+            let constant = usize::try_from(arg.value)
+                .ok()
+                .and_then(|index| function.constants.get(index))
+                .and_then(|constant| constant.value.as_ref())
+                .filter(|_| arg.mode == HSOpArgMode::CONST);
+            let _ = match constant {
+                Some(HSValue::String(s)) => write!(text, " CONST({s:?})"),
+                Some(HSValue::Number(n)) => write!(text, " CONST({n})"),
+                Some(HSValue::Boolean(b)) => write!(text, " CONST({b})"),
+                Some(HSValue::LightUserData(n) | HSValue::Ui64(n)) => write!(text, " CONST({n})"),
+                Some(HSValue::Nil) => write!(text, " CONST(nil)"),
+                None => write!(text, " {}({})", arg.mode, arg.value),
+            };
+        }
+        text
+    }
+
+    /// Labels an edge with the result of the condition that leads along it.
+    fn edge_label(
+        function: &HSFunction,
+        graph: &HSControlFlowGraph,
+        edge: &HSEdge,
+    ) -> Option<&'static str> {
+        if edge.kind == HSEdgeKind::LoopBack {
+            return Some("loop");
+        }
+        let instruction = &function.instructions[graph.blocks[edge.from].last()];
+        let result = match (instruction.mode, edge.kind) {
+            (HSOpCode::ForLoop, HSEdgeKind::Jump) => true,
+            (HSOpCode::ForLoop, HSEdgeKind::Fallthrough) => false,
+            (_, HSEdgeKind::Fallthrough) => Self::runs_next_when(instruction)?,
+            (_, HSEdgeKind::ConditionalSkip) => !Self::runs_next_when(instruction)?,
+            _ => return None,
+        };
+        Some(if result { "true" } else { "false" })
+    }
+
+    /// Returns the result of a comparison or test for which it runs the next instruction
+    /// instead of skipping it: comparisons skip unless their result matches A, and tests skip
+    /// unless the truth of the tested register matches C.
+    fn runs_next_when(instruction: &HSInstruction) -> Option<bool> {
+        let flag = |arg: Option<&HSInstructionArg>| arg.is_some_and(|arg| arg.value != 0);
+        match instruction.mode {
+            HSOpCode::Eq
+            | HSOpCode::EqBk
+            | HSOpCode::Lt
+            | HSOpCode::LtBk
+            | HSOpCode::Le
+            | HSOpCode::LeBk => Some(flag(instruction.args.first())),
+            // C is the last argument, as tests leave B unused apart from `TestSet`.
+            HSOpCode::Test | HSOpCode::TestR1 | HSOpCode::TestSet => {
+                Some(flag(instruction.args.last()))
+            }
+            HSOpCode::TForLoop => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Escapes text for use in a quoted DOT string.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl Display for HSDotGraph<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let id = match self.function.id.as_str() {
+            "" => "0".to_string(),
+            id => id.replace('/', "_"),
+        };
+        if !self.clusters {
+            return Self::fmt_graph(f, self.function, &id);
+        }
+        writeln!(f, "digraph \"script\" {{")?;
+        writeln!(f, "    compound=true;")?;
+        writeln!(f, "    node [shape=box, fontname=\"monospace\"];")?;
+        Self::fmt_cluster(f, self.function, &id, 1)?;
+        writeln!(f, "}}")
+    }
+}
@@ -0,0 +1,65 @@
+//! Natural loop detection for control-flow graphs.
+
+use super::{HSControlFlowGraph, HSEdge, HSEdgeKind};
+
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone)]
+/// Natural loop, formed by the back edges entering the same header.
+pub struct HSLoop {
+    /// Block every iteration starts at, which dominates the whole loop.
+    pub header: usize,
+    /// Blocks with a back edge to the header.
+    pub latches: Vec<usize>,
+    /// Blocks in the loop, including the header and latches.
+    pub body: BTreeSet<usize>,
+}
+
+impl HSLoop {
+    /// Returns the edges leaving the loop.
+    pub fn exits<'a>(&'a self, graph: &'a HSControlFlowGraph) -> impl Iterator<Item = &'a HSEdge> {
+        self.body
+            .iter()
+            .flat_map(|block| graph.successors(*block))
+            .filter(|edge| !self.body.contains(&edge.to))
+    }
+}
+
+/// Finds the natural loops of a graph whose back edges are already marked as `LoopBack`.
+#[must_use]
+pub fn find_loops(graph: &HSControlFlowGraph) -> Vec<HSLoop> {
+    let mut loops: Vec<HSLoop> = Vec::new();
+    for edge in &graph.edges {
+        if edge.kind != HSEdgeKind::LoopBack {
+            continue;
+        }
+
+        // The body is everything that reaches the latch without going through the header.
+        let mut body = BTreeSet::from([edge.to, edge.from]);
+        let mut work = vec![edge.from];
+        while let Some(block) = work.pop() {
+            if block == edge.to {
+                continue;
+            }
+            for predecessor in graph.predecessors(block) {
+                if graph.is_reachable(predecessor.from) && body.insert(predecessor.from) {
+                    work.push(predecessor.from);
+                }
+            }
+        }
+
+        match loops.iter_mut().find(|lp| lp.header == edge.to) {
+            Some(lp) => {
+                lp.latches.push(edge.from);
+                lp.body.extend(body);
+            }
+            None => loops.push(HSLoop {
+                header: edge.to,
+                latches: vec![edge.from],
+                body,
+            }),
+        }
+    }
+    loops.sort_by_key(|lp| lp.header);
+    loops
+}
@@ -0,0 +1,259 @@
+//! Control-flow graphs of `HavokScript` functions.
+//!
+//! Each `HSFunction` is split into basic blocks, maximal runs of instructions that are only
+//! entered at their first instruction and only left after their last one. Blocks are linked by
+//! typed edges following the Lua 5.1 semantics of each instruction, including the implicit skip
+//! of the instruction following a comparison or test.
+
+pub mod dominators;
+pub mod dot;
+pub mod loops;
+
+use crate::loader::{hs_function::HSFunction, hs_instruction::HSInstruction, hs_opcodes::HSOpCode};
+use dominators::compute_dominators;
+use loops::{HSLoop, find_loops};
+
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How control moves along an edge.
+pub enum HSEdgeKind {
+    /// Execution continues with the next instruction.
+    Fallthrough,
+    /// Unconditional jump, such as `Jmp`, `ForPrep` or a `LoadBool` that skips the next instruction.
+    Jump,
+    /// Taken when a comparison or test doesn't match the expected result, skipping the next instruction.
+    ConditionalSkip,
+    /// Edge to a block that dominates its source, closing a loop.
+    LoopBack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Edge between two basic blocks.
+pub struct HSEdge {
+    /// Index of the block the edge leaves.
+    pub from: usize,
+    /// Index of the block the edge enters.
+    pub to: usize,
+    /// How control moves along the edge.
+    pub kind: HSEdgeKind,
+}
+
+#[derive(Debug, Clone)]
+/// Maximal run of instructions that is only entered at its start and only left at its end.
+pub struct HSBasicBlock {
+    /// Index of the first instruction in the block.
+    pub start: usize,
+    /// Index one past the last instruction in the block.
+    pub end: usize,
+    /// Indices of the edges leaving the block.
+    pub successors: Vec<usize>,
+    /// Indices of the edges entering the block.
+    pub predecessors: Vec<usize>,
+}
+
+impl HSBasicBlock {
+    /// Index of the last instruction in the block.
+    #[must_use]
+    pub fn last(&self) -> usize {
+        self.end - 1
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// Control-flow graph of a single function.
+pub struct HSControlFlowGraph {
+    /// Basic blocks, ordered by their first instruction. The entry block is always the first one.
+    pub blocks: Vec<HSBasicBlock>,
+    /// Edges between blocks.
+    pub edges: Vec<HSEdge>,
+    /// Immediate dominator of each block, `None` for the entry block and unreachable blocks.
+    pub immediate_dominators: Vec<Option<usize>>,
+    /// Natural loops, ordered by header.
+    pub loops: Vec<HSLoop>,
+}
+
+/// Where control may go after executing an instruction.
+enum Flow {
+    /// Only the next instruction.
+    Next,
+    /// Only the given instruction.
+    Jump(Option<usize>),
+    /// The next instruction, or the one after it.
+    Skip,
+    /// The next instruction, or the given one.
+    Branch(Option<usize>),
+    /// Leaves the function.
+    Exit,
+}
+
+impl HSControlFlowGraph {
+    /// Builds the control-flow graph of a function, without its children.
+    #[must_use]
+    pub fn new(function: &HSFunction) -> Self {
+        let instructions = &function.instructions;
+        if instructions.is_empty() {
+            return Self::default();
+        }
+
+        // Block boundaries are jump targets and instructions following a change in control flow.
+        let mut leaders = BTreeSet::from([0]);
+        for (pc, instruction) in instructions.iter().enumerate() {
+            let targets = match Self::flow(instruction, pc, instructions.len()) {
+                Flow::Next => continue,
+                Flow::Jump(target) | Flow::Branch(target) => vec![target, Some(pc + 1)],
+                Flow::Skip => vec![Some(pc + 1), Some(pc + 2)],
+                Flow::Exit => vec![Some(pc + 1)],
+            };
+            leaders.extend(
+                targets
+                    .into_iter()
+                    .flatten()
+                    .filter(|target| *target < instructions.len()),
+            );
+        }
+
+        let starts = leaders.into_iter().collect::<Vec<_>>();
+        let mut graph = Self {
+            blocks: starts
+                .iter()
+                .enumerate()
+                .map(|(index, start)| HSBasicBlock {
+                    start: *start,
+                    end: starts.get(index + 1).copied().unwrap_or(instructions.len()),
+                    successors: Vec::new(),
+                    predecessors: Vec::new(),
+                })
+                .collect(),
+            ..Self::default()
+        };
+
+        for block in 0..graph.blocks.len() {
+            let last = graph.blocks[block].last();
+            let targets = match Self::flow(&instructions[last], last, instructions.len()) {
+                Flow::Next => vec![(Some(last + 1), HSEdgeKind::Fallthrough)],
+                Flow::Jump(target) => vec![(target, HSEdgeKind::Jump)],
+                Flow::Skip => vec![
+                    (Some(last + 1), HSEdgeKind::Fallthrough),
+                    (Some(last + 2), HSEdgeKind::ConditionalSkip),
+                ],
+                Flow::Branch(target) => vec![
+                    (Some(last + 1), HSEdgeKind::Fallthrough),
+                    (target, HSEdgeKind::Jump),
+                ],
+                Flow::Exit => Vec::new(),
+            };
+            for (target, kind) in targets {
+                if let Some(to) = target.and_then(|target| graph.block_at(target)) {
+                    graph.add_edge(block, to, kind);
+                }
+            }
+        }
+
+        graph.immediate_dominators = compute_dominators(&graph);
+        for edge in &mut graph.edges {
+            if Self::dominates_in(&graph.immediate_dominators, edge.to, edge.from) {
+                edge.kind = HSEdgeKind::LoopBack;
+            }
+        }
+        graph.loops = find_loops(&graph);
+        graph
+    }
+
+    /// Builds the control-flow graphs of a function and all of its children, in pre-order.
+    #[must_use]
+    pub fn new_recursive(function: &HSFunction) -> Vec<Self> {
+        let mut graphs = vec![Self::new(function)];
+        for child in &function.child_functions {
+            graphs.extend(Self::new_recursive(child));
+        }
+        graphs
+    }
+
+    /// Returns where control may go after the instruction at `pc`.
+    fn flow(instruction: &HSInstruction, pc: usize, len: usize) -> Flow {
+        let target = || instruction.jump_target(pc).filter(|target| *target < len);
+        match instruction.mode {
+            HSOpCode::Jmp | HSOpCode::ForPrep => Flow::Jump(target()),
+            HSOpCode::ForLoop => Flow::Branch(target()),
+            _ if instruction.skips_next() => Flow::Skip,
+            HSOpCode::LoadBool if instruction.args.get(2).is_some_and(|arg| arg.value != 0) => {
+                Flow::Jump(Some(pc + 2).filter(|target| *target < len))
+            }
+            HSOpCode::Return => Flow::Exit,
+            _ => Flow::Next,
+        }
+    }
+
+    fn add_edge(&mut self, from: usize, to: usize, kind: HSEdgeKind) {
+        let index = self.edges.len();
+        self.edges.push(HSEdge { from, to, kind });
+        self.blocks[from].successors.push(index);
+        self.blocks[to].predecessors.push(index);
+    }
+
+    /// Returns the index of the block containing the instruction at `pc`.
+    #[must_use]
+    pub fn block_at(&self, pc: usize) -> Option<usize> {
+        let index = self.blocks.partition_point(|block| block.start <= pc);
+        index
+            .checked_sub(1)
+            .filter(|index| pc < self.blocks[*index].end)
+    }
+
+    /// Returns the edges leaving a block.
+    pub fn successors(&self, block: usize) -> impl Iterator<Item = &HSEdge> {
+        self.blocks[block]
+            .successors
+            .iter()
+            .map(|edge| &self.edges[*edge])
+    }
+
+    /// Returns the edges entering a block.
+    pub fn predecessors(&self, block: usize) -> impl Iterator<Item = &HSEdge> {
+        self.blocks[block]
+            .predecessors
+            .iter()
+            .map(|edge| &self.edges[*edge])
+    }
+
+    /// Returns whether every path from the entry to `block` goes through `dominator`.
+    #[must_use]
+    pub fn dominates(&self, dominator: usize, block: usize) -> bool {
+        Self::dominates_in(&self.immediate_dominators, dominator, block)
+    }
+
+    fn dominates_in(
+        immediate_dominators: &[Option<usize>],
+        dominator: usize,
+        block: usize,
+    ) -> bool {
+        // Unreachable blocks have no dominators, including themselves.
+        if block != 0 && immediate_dominators[block].is_none() {
+            return false;
+        }
+        let mut current = Some(block);
+        while let Some(node) = current {
+            if node == dominator {
+                return true;
+            }
+            current = immediate_dominators[node];
+        }
+        false
+    }
+
+    /// Returns whether a block can be reached from the entry block.
+    #[must_use]
+    pub fn is_reachable(&self, block: usize) -> bool {
+        block == 0 || self.immediate_dominators[block].is_some()
+    }
+
+    /// Returns the innermost loop containing a block.
+    #[must_use]
+    pub fn innermost_loop(&self, block: usize) -> Option<&HSLoop> {
+        self.loops
+            .iter()
+            .filter(|lp| lp.body.contains(&block))
+            .min_by_key(|lp| lp.body.len())
+    }
+}
@@ -1,4 +1,4 @@
-use std::{num::TryFromIntError, string::FromUtf8Error};
+use std::{io::Seek, num::TryFromIntError, string::FromUtf8Error};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -28,6 +28,14 @@ pub enum HkscError {
     /// String length can be either 4 or 8 bytes.
     /// This error occurs when a value other than those are found.
     InvalidStringSize(u8),
+    #[error("Invalid integer size: {0}!")]
+    /// Integers, such as the counts of a function, can be either 4 or 8 bytes.
+    /// This error occurs when a value other than those are found.
+    InvalidIntSize(u8),
+    #[error("Invalid type size: {0}!")]
+    /// Sizes, such as instruction counts and structure ids, can be either 4 or 8 bytes.
+    /// This error occurs when a value other than those are found.
+    InvalidTypeSize(u8),
     #[error("Integer overflow!")]
     /// This error occurs when an integer cast overflows.
     TryFromInt(#[from] TryFromIntError),
@@ -43,4 +51,78 @@ pub enum HkscError {
     #[error("Unsupported endianness: Little Endian")]
     /// This error occurs when a little endian file is found, which is currently not supported.
     UnsupportedEndianness,
+    #[error("Unsupported instruction size: {0}!")]
+    /// Only 4 byte instructions are supported.
+    /// This error occurs when a header or an `HSInstructionEncoding` declares another size.
+    UnsupportedInstructionSize(u8),
+    #[error("Unknown opcode {opcode} in instruction {raw:#010x}!")]
+    /// This error occurs when an instruction's opcode is outside of `OP_TABLE`.
+    /// Reading with `HSReadOptions::lenient` keeps these as `HSOpCode::Unknown` instead.
+    /// `offset` is where the instruction starts, which `HkscError::within` reports it at.
+    UnknownOpcode { offset: u64, raw: u64, opcode: u8 },
+    #[error("Opcode {opcode} is not mapped by the opcode profile `{profile}`!")]
+    /// This error occurs when an instruction is encoded with a profile that has no number for its opcode.
+    UnmappedOpcode { opcode: String, profile: String },
+    #[error("Invalid opcode profile: {0}!")]
+    /// This error occurs when an opcode profile cannot be loaded, or names an unknown preset.
+    InvalidOpcodeProfile(String),
+    #[error("{source} (in {}, at offset {offset:#x})", .path.join(" > "))]
+    /// Wraps an error raised while reading with where it happened: the offset the reader had
+    /// reached and the path of the element being read, such as `main > child[3] > constants[17]`.
+    Context {
+        offset: u64,
+        path: Vec<String>,
+        source: Box<HkscError>,
+    },
+    #[error("No function with id {0}!")]
+    /// This error occurs when a function id, such as `0/3/1`, doesn't match any function.
+    FunctionNotFound(String),
+    #[error("Listings can only be written for whole files!")]
+    /// This error occurs when `--format listing` is combined with `--function`.
+    PartialListing,
+    #[error("Verification failed with {0} violation(s)!")]
+    /// This error occurs when `--verify` finds violations in a file.
+    VerificationFailed(usize),
+    #[error("Verification failed, as the file was only partially read!")]
+    /// This error occurs when `--verify` is combined with `--recover` and the file couldn't be
+    /// fully read, even if no violations were found in what was read.
+    IncompleteVerification,
+    #[error("Assembly error on line {line}: {message}!")]
+    /// This error occurs when a listing cannot be assembled, with the line number it occurred on.
+    AssemblyError { line: usize, message: String },
+    #[cfg(feature = "serde")]
+    #[error("JSON serialization error: {0}!")]
+    /// This error occurs when a parsed file cannot be serialized to JSON.
+    JsonError(#[from] serde_json::Error),
+}
+
+impl HkscError {
+    /// Adds `segment` to the front of the error's path, wrapping it in `HkscError::Context` if it
+    /// doesn't have a path yet. The context takes the offset of the error itself when it has one,
+    /// and the current offset of `reader` otherwise.
+    #[must_use]
+    pub fn within(self, segment: impl Into<String>, reader: &mut impl Seek) -> Self {
+        match self {
+            Self::Context {
+                offset,
+                mut path,
+                source,
+            } => {
+                path.insert(0, segment.into());
+                Self::Context {
+                    offset,
+                    path,
+                    source,
+                }
+            }
+            error => Self::Context {
+                offset: match error {
+                    Self::UnknownOpcode { offset, .. } => offset,
+                    _ => reader.stream_position().unwrap_or_default(),
+                },
+                path: vec![segment.into()],
+                source: Box::new(error),
+            },
+        }
+    }
 }
@@ -1,16 +1,21 @@
-//! Extensions to `BufReader`.
+//! Extensions to buffered readers and seekable writers.
 //!
-//! Implements additional reading methods for `BufReader`:
+//! Implements additional reading methods for any `BufRead + Seek` reader, such as `BufReader` or `Cursor`:
 //! * `read_fixed_string`: Reads a fixed-length string from a buffer.
 //! * `read_enumerable`: Reads multiple instances of a type that implements `Readable` into a `Vec`.
 //! * `read_header_enumerable`: Reads multiple instances of a type that implements `HeaderReadable` into a `Vec`.
 //!
-//! These extensions require `Read + Seek` bounds.
+//! And the matching writing methods for any `Write + Seek` writer:
+//! * `write_fixed_string`: Writes a string into a fixed-length field.
+//! * `write_enumerable`: Writes a slice of a type that implements `Writable`.
+//! * `write_header_enumerable`: Writes a slice of a type that implements `HeaderWritable`.
+//!
+//! These extensions require `Read + Seek` and `Write + Seek` bounds respectively.
 
 use crate::{common::errors::HkscError, loader::hs_header::HSHeader};
 
 use byteorder::ByteOrder;
-use std::io::{BufRead, BufReader, Read, Seek};
+use std::io::{BufRead, Read, Seek, Write};
 
 /// `Readable` trait that ensures a `read` method is declared.
 pub trait Readable {
@@ -28,7 +33,23 @@ pub trait HeaderReadable {
     ) -> Result<(), HkscError>;
 }
 
-/// Extension trait for `BufReader` to add custom reading methods.
+/// `Writable` trait that ensures a `write` method is declared.
+pub trait Writable {
+    /// Writes data to a writer implementing `Write`, `WriterExt`, and `Seek`.
+    fn write<T: ByteOrder>(&self, writer: &mut impl WriterExt) -> Result<(), HkscError>;
+}
+
+/// `HeaderWritable` trait that ensures a `write` method is declared with a `HSHeader` argument.
+pub trait HeaderWritable {
+    /// Writes data to a writer implementing `Write`, `WriterExt`, and `Seek`, using header information.
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError>;
+}
+
+/// Extension trait for buffered readers to add custom reading methods.
 pub trait BufReaderExt: BufRead
 where
     Self: Seek,
@@ -40,9 +61,19 @@ where
     ///
     /// # Returns
     /// The read string on success, or an error on failure.
-    fn read_fixed_string<T: ByteOrder>(&mut self, length: usize) -> Result<String, HkscError> {
-        let mut buffer = vec![0; length];
-        self.read_exact(&mut buffer)?;
+    fn read_fixed_string<T: ByteOrder>(&mut self, length: usize) -> Result<String, HkscError>
+    where
+        Self: Sized,
+    {
+        // The buffer grows as data is read, so a corrupt length fails at the end of the
+        // data instead of allocating the whole length up front.
+        let mut buffer = Vec::new();
+        self.by_ref()
+            .take(u64::try_from(length)?)
+            .read_to_end(&mut buffer)?;
+        if buffer.len() != length {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
 
         // Goes through the string to remove the null terminator
         // I *guess* iterating through a string is expensive
@@ -58,21 +89,25 @@ where
     ///
     /// # Arguments
     /// * `count` - Number of instances to read.
+    /// * `name` - Name of the list, used in the path of errors such as `name[3]`.
     ///
     /// # Returns
     /// `Vec` of read instances on success, or an error on failure.
     fn read_enumerable<T: Default + Readable, R: ByteOrder>(
         &mut self,
         count: u64,
+        name: &str,
     ) -> Result<Vec<T>, HkscError>
     where
         Self: Sized,
         Vec<T>: FromIterator<T>,
     {
         let enumerables = (0..count)
-            .map(|_| -> Result<T, HkscError> {
+            .map(|index| -> Result<T, HkscError> {
                 let mut enumerable = T::default();
-                enumerable.read::<R>(self)?;
+                enumerable
+                    .read::<R>(self)
+                    .map_err(|error| error.within(format!("{name}[{index}]"), self))?;
                 Ok(enumerable)
             })
             .collect::<Result<Vec<_>, _>>()?;
@@ -84,6 +119,7 @@ where
     /// # Arguments
     /// * `count` - Number of instances to read.
     /// * `header` - The `HSHeader` containing format information.
+    /// * `name` - Name of the list, used in the path of errors such as `name[3]`.
     ///
     /// # Returns
     /// `Vec` of read instances on success, or an error on failure.
@@ -91,15 +127,18 @@ where
         &mut self,
         count: u64,
         header: &HSHeader,
+        name: &str,
     ) -> Result<Vec<T>, HkscError>
     where
         Self: Sized,
         Vec<T>: FromIterator<T>,
     {
         let enumerables = (0..count)
-            .map(|_| -> Result<T, HkscError> {
+            .map(|index| -> Result<T, HkscError> {
                 let mut enumerable = T::default();
-                enumerable.read::<R>(self, header)?;
+                enumerable
+                    .read::<R>(self, header)
+                    .map_err(|error| error.within(format!("{name}[{index}]"), self))?;
                 Ok(enumerable)
             })
             .collect::<Result<Vec<_>, _>>()?;
@@ -107,4 +146,66 @@ where
     }
 }
 
-impl<R: Read + Seek> BufReaderExt for BufReader<R> {}
+impl<R: BufRead + Seek> BufReaderExt for R {}
+
+/// Extension trait for seekable writers to add custom writing methods.
+pub trait WriterExt: Write
+where
+    Self: Seek,
+{
+    /// Writes a UTF-8 encoded string into a fixed-length field.
+    /// The string is padded with null bytes, or truncated if it does not fit.
+    ///
+    /// # Arguments
+    /// * `value` - String to write.
+    /// * `length` - Number of bytes to write.
+    fn write_fixed_string<T: ByteOrder>(
+        &mut self,
+        value: &str,
+        length: usize,
+    ) -> Result<(), HkscError> {
+        let mut buffer = vec![0; length];
+        let copied = value.len().min(length);
+        buffer[..copied].copy_from_slice(&value.as_bytes()[..copied]);
+        self.write_all(&buffer)?;
+        Ok(())
+    }
+
+    /// Writes multiple instances of a type in order.
+    ///
+    /// # Arguments
+    /// * `enumerables` - Instances to write.
+    fn write_enumerable<T: Writable, R: ByteOrder>(
+        &mut self,
+        enumerables: &[T],
+    ) -> Result<(), HkscError>
+    where
+        Self: Sized,
+    {
+        for enumerable in enumerables {
+            enumerable.write::<R>(self)?;
+        }
+        Ok(())
+    }
+
+    /// Writes multiple instances of a type in order, using header information.
+    ///
+    /// # Arguments
+    /// * `enumerables` - Instances to write.
+    /// * `header` - The `HSHeader` containing format information.
+    fn write_header_enumerable<T: HeaderWritable, R: ByteOrder>(
+        &mut self,
+        enumerables: &[T],
+        header: &HSHeader,
+    ) -> Result<(), HkscError>
+    where
+        Self: Sized,
+    {
+        for enumerable in enumerables {
+            enumerable.write::<R>(self, header)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> WriterExt for W {}
@@ -0,0 +1,264 @@
+//! Lua expressions produced while lifting instructions, along with their precedence.
+
+use crate::loader::hs_constant::{HSConstant, HSValue};
+
+use std::fmt::Write;
+
+/// Precedence of `or`.
+pub const OR: u8 = 1;
+/// Precedence of `and`.
+pub const AND: u8 = 2;
+/// Precedence of comparison operators.
+pub const COMPARE: u8 = 3;
+/// Precedence of `..`, which is right associative.
+pub const CONCAT: u8 = 4;
+/// Precedence of `+` and `-`.
+pub const ADD: u8 = 5;
+/// Precedence of `*`, `/` and `%`.
+pub const MUL: u8 = 6;
+/// Precedence of `not`, `#` and unary `-`.
+pub const UNARY: u8 = 7;
+/// Precedence of `^`, which is right associative.
+pub const POW: u8 = 8;
+/// Precedence of names, calls, indexing and literals.
+pub const ATOM: u8 = 9;
+
+const KEYWORDS: [&str; 21] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// What kind of value an expression holds, when that matters to how it is consumed.
+#[derive(Clone, PartialEq)]
+pub enum ExprKind {
+    /// Any other expression.
+    Value,
+    /// A literal, which needs parentheses to be called or indexed.
+    Literal,
+    /// A function call returning a single value, which has side effects.
+    Call,
+    /// An expression returning several values, such as a call or `...`.
+    /// Holds the number of values, or `None` if all values are kept.
+    Multi(Option<u32>),
+    /// A register filled by the `Multi` expression in a lower register.
+    Continuation,
+    /// A method looked up by `SelfOp`, holding the object and method key.
+    Method(String, Box<Expr>),
+    /// The object copied into the register after a method by `SelfOp`.
+    SelfArg,
+    /// A table constructor that is still being filled.
+    Table(Vec<String>),
+    /// A closure, which can use the `function name()` syntax when assigned.
+    Function,
+}
+
+/// A Lua expression.
+#[derive(Clone, PartialEq)]
+pub struct Expr {
+    /// Source text of the expression.
+    pub text: String,
+    /// Precedence of the outermost operator.
+    pub precedence: u8,
+    /// Kind of the expression.
+    pub kind: ExprKind,
+}
+
+impl Expr {
+    #[must_use]
+    pub fn new(text: impl Into<String>, precedence: u8, kind: ExprKind) -> Self {
+        Self {
+            text: text.into(),
+            precedence,
+            kind,
+        }
+    }
+
+    /// Creates a name, call or index expression.
+    #[must_use]
+    pub fn atom(text: impl Into<String>) -> Self {
+        Self::new(text, ATOM, ExprKind::Value)
+    }
+
+    /// Creates a literal from a constant.
+    #[must_use]
+    pub fn constant(constant: Option<&HSConstant>) -> Self {
+        let text = match constant.and_then(|constant| constant.value.as_ref()) {
+            Some(HSValue::Nil) | None => "nil".to_string(),
+            Some(HSValue::Boolean(b)) => b.to_string(),
+            Some(HSValue::Number(n)) => format_number(*n),
+            Some(HSValue::String(s)) => quote(s),
+            Some(HSValue::LightUserData(n) | HSValue::Ui64(n)) => n.to_string(),
+        };
+        let precedence = if text.starts_with('-') { UNARY } else { ATOM };
+        Self::new(text, precedence, ExprKind::Literal)
+    }
+
+    /// Returns the text, wrapped in parentheses if its precedence is below `precedence`.
+    #[must_use]
+    pub fn wrap(&self, precedence: u8) -> String {
+        if self.precedence < precedence {
+            format!("({})", self.text)
+        } else {
+            self.text.clone()
+        }
+    }
+
+    /// Returns the text in a form that can be called or indexed.
+    #[must_use]
+    pub fn prefix(&self) -> String {
+        if self.kind == ExprKind::Literal || self.precedence < ATOM {
+            format!("({})", self.text)
+        } else {
+            self.text.clone()
+        }
+    }
+
+    /// Returns the string this expression holds, if it is a string literal that is a valid name.
+    #[must_use]
+    pub fn as_name(&self) -> Option<&str> {
+        if self.kind != ExprKind::Literal {
+            return None;
+        }
+        let name = self.text.strip_prefix('"')?.strip_suffix('"')?;
+        is_name(name).then_some(name)
+    }
+
+    /// Indexes `object` with `key`, using `object.key` where possible.
+    #[must_use]
+    pub fn index(object: &Expr, key: &Expr) -> Self {
+        match key.as_name() {
+            Some(name) => Self::atom(format!("{}.{name}", object.prefix())),
+            None => Self::atom(format!("{}[{}]", object.prefix(), key.text)),
+        }
+    }
+
+    /// Combines two expressions with a binary operator.
+    #[must_use]
+    pub fn binary(left: &Expr, operator: &str, right: &Expr, precedence: u8) -> Self {
+        let right_associative = precedence == CONCAT || precedence == POW;
+        let (left_min, right_min) = if right_associative {
+            (precedence + 1, precedence)
+        } else {
+            (precedence, precedence + 1)
+        };
+        Self::new(
+            format!(
+                "{} {operator} {}",
+                left.wrap(left_min),
+                right.wrap(right_min)
+            ),
+            precedence,
+            ExprKind::Value,
+        )
+    }
+
+    /// Applies a unary operator to an expression.
+    #[must_use]
+    pub fn unary(operator: &str, operand: &Expr) -> Self {
+        let operand = operand.wrap(UNARY);
+        let separator = if operator == "not" || (operator == "-" && operand.starts_with('-')) {
+            " "
+        } else {
+            ""
+        };
+        Self::new(
+            format!("{operator}{separator}{operand}"),
+            UNARY,
+            ExprKind::Value,
+        )
+    }
+}
+
+/// A branch condition, kept structured so that it can be negated.
+#[derive(Clone)]
+pub enum Condition {
+    /// Comparison of two expressions.
+    Compare(Expr, &'static str, Expr),
+    /// Truthiness test of an expression, negated if the flag is set.
+    Test(Expr, bool),
+    /// Two conditions joined with `and` if the flag is set, `or` otherwise.
+    Logical(Box<Condition>, bool, Box<Condition>),
+}
+
+impl Condition {
+    /// Returns the opposite condition.
+    #[must_use]
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Compare(left, operator, right) => {
+                let operator = match operator {
+                    "==" => "~=",
+                    "~=" => "==",
+                    "<" => ">=",
+                    ">=" => "<",
+                    "<=" => ">",
+                    _ => "<=",
+                };
+                Self::Compare(left, operator, right)
+            }
+            Self::Test(value, negated) => Self::Test(value, !negated),
+            Self::Logical(left, is_and, right) => {
+                Self::Logical(Box::new(left.negate()), !is_and, Box::new(right.negate()))
+            }
+        }
+    }
+
+    /// Converts the condition into an expression.
+    #[must_use]
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            Self::Compare(left, operator, right) => Expr::binary(left, operator, right, COMPARE),
+            Self::Test(value, false) => value.clone(),
+            Self::Test(value, true) => Expr::unary("not", value),
+            Self::Logical(left, true, right) => {
+                Expr::binary(&left.to_expr(), "and", &right.to_expr(), AND)
+            }
+            Self::Logical(left, false, right) => {
+                Expr::binary(&left.to_expr(), "or", &right.to_expr(), OR)
+            }
+        }
+    }
+}
+
+/// Returns whether `name` can be used as a Lua identifier.
+#[must_use]
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Formats a number as a Lua literal.
+fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "(0/0)".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "math.huge" } else { "-math.huge" }.to_string()
+    } else {
+        n.to_string()
+    }
+}
+
+/// Quotes a string as a Lua literal.
+fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_ascii_control() => {
+                let _ = write!(quoted, "\\{:03}", u32::from(c));
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,1306 @@
+//! Lifts the instructions of a single `HSFunction` into Lua statements.
+//!
+//! Registers are tracked as pending expressions that get inlined into the instruction reading them.
+//! Values that end up in named locals are declared with `local`, anything else that has to be
+//! kept around is stored in a temporary named after its register (such as `r3`).
+//! Control flow is recovered by matching the jump patterns the Lua 5.1 compiler emits for
+//! `if`, `while`, `repeat` and both kinds of `for` loops.
+
+use super::{
+    expression::{ADD, AND, ATOM, CONCAT, Condition, Expr, ExprKind, MUL, OR, POW, is_name},
+    statement::{Statement, render},
+};
+use crate::loader::{
+    hs_function::{FIELDS_PER_FLUSH, HSFunction, HSVarArg},
+    hs_instruction::HSInstruction,
+    hs_opcodes::{HSOpArgMode, HSOpCode},
+    hs_structure::HSStructPrototype,
+};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Value waiting in a register to be inlined into the expression that reads it.
+struct Pending {
+    expr: Expr,
+    /// Order in which pending values were created, used to keep side effects in order.
+    sequence: usize,
+}
+
+/// Local variable from debug info, with the register it lives in.
+struct Local {
+    name: String,
+    register: usize,
+    start: usize,
+    end: usize,
+    declared: bool,
+}
+
+/// Chain of conditional jumps making up a single (possibly compound) condition.
+struct Chain {
+    /// Program counters of each condition instruction, with the target of the jump following it.
+    pairs: Vec<(usize, usize)>,
+    /// First instruction of the block that runs when the condition holds.
+    body: usize,
+    /// Target when the condition does not hold.
+    exit: usize,
+}
+
+/// Decompiler for the body of a single function.
+pub struct FunctionDecompiler<'a> {
+    function: &'a HSFunction,
+    /// Structure prototypes that the structures created by `NewStruct` are named after.
+    structs: &'a [HSStructPrototype],
+    locals: Vec<Local>,
+    /// Names of the registers holding parameters, without the `...` of vararg functions.
+    parameters: Vec<String>,
+    pending: BTreeMap<usize, Pending>,
+    sequence: usize,
+    /// Register holding a value that extends up to the top of the stack, if any.
+    multi: Option<usize>,
+    /// Registers that had to be stored in temporaries.
+    temporaries: BTreeSet<usize>,
+    /// Instruction after the innermost loop, which `break` jumps to.
+    loop_exits: Vec<usize>,
+    /// Register whose value is kept pending even if it belongs to a local.
+    capture: Option<usize>,
+}
+
+impl<'a> FunctionDecompiler<'a> {
+    #[must_use]
+    pub fn new(function: &'a HSFunction, structs: &'a [HSStructPrototype]) -> Self {
+        let mut locals: Vec<Local> = Vec::new();
+        if function.has_debug_info {
+            for local in &function.debug_info.locals {
+                let start = local.start as usize;
+                let register = locals
+                    .iter()
+                    .filter(|other| other.start <= start && start < other.end)
+                    .count();
+                locals.push(Local {
+                    name: local.local_name.clone(),
+                    register,
+                    start,
+                    end: local.end as usize,
+                    declared: false,
+                });
+            }
+        }
+
+        // Parameters live in registers, so a corrupt count can't make up more of them than the
+        // function has.
+        let parameter_count = function.param_count.min(function.slot_count) as usize;
+        let mut parameters = Vec::new();
+        for register in 0..parameter_count {
+            let local = locals
+                .iter_mut()
+                .find(|local| local.register == register && local.start == 0 && !local.declared);
+            if let Some(local) = local {
+                local.declared = true;
+                parameters.push(local.name.clone());
+            } else {
+                parameters.push(format!("r{register}"));
+            }
+        }
+
+        Self {
+            function,
+            structs,
+            locals,
+            parameters,
+            pending: BTreeMap::new(),
+            sequence: 0,
+            multi: None,
+            temporaries: BTreeSet::new(),
+            loop_exits: Vec::new(),
+            capture: None,
+        }
+    }
+
+    /// Decompiles the function body into source text at the given indentation level.
+    #[must_use]
+    pub fn decompile(mut self, depth: usize) -> String {
+        let mut body = Vec::new();
+        self.declare_locals(0, &mut body);
+        self.block(0, self.function.instructions.len(), &mut body);
+        self.flush_all(&mut body);
+
+        let temporaries = self
+            .temporaries
+            .iter()
+            .filter(|register| {
+                self.parameters
+                    .get(**register)
+                    .is_none_or(|parameter| *parameter != format!("r{register}"))
+            })
+            .map(|register| format!("r{register}"))
+            .collect::<Vec<_>>();
+        if !temporaries.is_empty() {
+            body.insert(
+                0,
+                Statement::Line(format!("local {}", temporaries.join(", "))),
+            );
+        }
+
+        let mut out = String::new();
+        render(&body, depth, &mut out);
+        out
+    }
+
+    /// Decompiles the function as a closure expression.
+    #[must_use]
+    pub fn decompile_closure(self) -> String {
+        let mut parameters = self.parameters.clone();
+        if self.function.var_arg.contains(HSVarArg::ISVARARG) {
+            parameters.push("...".to_string());
+        }
+        let header = format!("function({})", parameters.join(", "));
+        format!("{header}\n{}end", self.decompile(1))
+    }
+
+    fn instruction(&self, pc: usize) -> Option<&'a HSInstruction> {
+        self.function.instructions.get(pc)
+    }
+
+    fn op(&self, pc: usize) -> Option<HSOpCode> {
+        self.instruction(pc).map(|instruction| instruction.mode)
+    }
+
+    fn arg(&self, pc: usize, index: usize) -> i32 {
+        self.instruction(pc)
+            .and_then(|instruction| instruction.args.get(index))
+            .map_or(0, |arg| arg.value)
+    }
+
+    fn reg(&self, pc: usize, index: usize) -> usize {
+        usize::try_from(self.arg(pc, index)).unwrap_or_default()
+    }
+
+    /// Returns where a `Jmp`, `ForPrep` or `ForLoop` instruction jumps to.
+    fn jump_target(&self, pc: usize) -> Option<usize> {
+        self.function
+            .instructions
+            .get(pc)?
+            .jump_target(pc)
+            .filter(|target| *target <= self.function.instructions.len())
+    }
+
+    fn is_condition(op: Option<HSOpCode>) -> bool {
+        matches!(
+            op,
+            Some(
+                HSOpCode::Eq
+                    | HSOpCode::EqBk
+                    | HSOpCode::Lt
+                    | HSOpCode::LtBk
+                    | HSOpCode::Le
+                    | HSOpCode::LeBk
+                    | HSOpCode::Test
+                    | HSOpCode::TestR1
+            )
+        )
+    }
+
+    fn active_local(&self, register: usize, pc: usize) -> Option<usize> {
+        self.locals.iter().rposition(|local| {
+            local.register == register
+                && local.start <= pc
+                && pc < local.end
+                && !local.name.starts_with('(')
+        })
+    }
+
+    /// Returns whether an instruction only computes a value, without emitting any statement.
+    fn is_pure(&self, pc: usize) -> bool {
+        self.computes_value(pc) && self.active_local(self.reg(pc, 0), pc + 1).is_none()
+    }
+
+    /// Returns whether an instruction only computes a value into its `A` register.
+    fn computes_value(&self, pc: usize) -> bool {
+        match self.op(pc) {
+            Some(
+                HSOpCode::Move
+                | HSOpCode::LoadK
+                | HSOpCode::LoadNil
+                | HSOpCode::GetGlobal
+                | HSOpCode::GetGlobalMem
+                | HSOpCode::GetUpval
+                | HSOpCode::GetField
+                | HSOpCode::GetFieldR1
+                | HSOpCode::GetFieldMm
+                | HSOpCode::GetTable
+                | HSOpCode::GetTableS
+                | HSOpCode::GetTableN
+                | HSOpCode::GetSlot
+                | HSOpCode::GetSlotMt
+                | HSOpCode::GetSlotD
+                | HSOpCode::SelfOp
+                | HSOpCode::SelfSlot
+                | HSOpCode::SelfSlotMt
+                | HSOpCode::Add
+                | HSOpCode::AddBk
+                | HSOpCode::Sub
+                | HSOpCode::SubBk
+                | HSOpCode::Mul
+                | HSOpCode::MulBk
+                | HSOpCode::Div
+                | HSOpCode::DivBk
+                | HSOpCode::Mod
+                | HSOpCode::ModBk
+                | HSOpCode::Pow
+                | HSOpCode::PowBk
+                | HSOpCode::Unm
+                | HSOpCode::Not
+                | HSOpCode::NotR1
+                | HSOpCode::Len
+                | HSOpCode::Concat,
+            ) => true,
+            Some(HSOpCode::LoadBool) => self.arg(pc, 2) == 0,
+            Some(HSOpCode::Call | HSOpCode::CallI | HSOpCode::CallC | HSOpCode::CallM) => {
+                self.arg(pc, 2) == 2
+            }
+            _ => false,
+        }
+    }
+
+    fn constant(&self, index: i32) -> Expr {
+        let constant = usize::try_from(index)
+            .ok()
+            .and_then(|index| self.function.constants.get(index));
+        Expr::constant(constant)
+    }
+
+    fn global(&self, index: i32) -> Expr {
+        let name = self.constant(index);
+        match name.as_name() {
+            Some(name) => Expr::atom(name),
+            None => Expr::atom(format!("_G[{}]", name.text)),
+        }
+    }
+
+    fn up_value(&self, index: i32) -> String {
+        usize::try_from(index)
+            .ok()
+            .filter(|_| self.function.has_debug_info)
+            .and_then(|index| self.function.debug_info.up_values.get(index))
+            .filter(|name| !name.is_empty())
+            .cloned()
+            .unwrap_or_else(|| format!("upval{index}"))
+    }
+
+    fn slot(object: &Expr, slot: i32) -> Expr {
+        Expr::atom(format!("{}.slot{slot}", object.prefix()))
+    }
+
+    /// Reads a register, inlining its pending value if it has one.
+    fn read(&mut self, register: usize, pc: usize) -> Expr {
+        if let Some(pending) = self.pending.remove(&register) {
+            if self.multi == Some(register) {
+                self.multi = None;
+            }
+            return match pending.expr.kind {
+                ExprKind::Table(items) => Self::table(&items),
+                ExprKind::Continuation => {
+                    self.temporaries.insert(register);
+                    Expr::atom(format!("r{register}"))
+                }
+                _ => pending.expr,
+            };
+        }
+        if let Some(local) = self.active_local(register, pc) {
+            return Expr::atom(self.locals[local].name.clone());
+        }
+        if let Some(parameter) = self.parameters.get(register) {
+            return Expr::atom(parameter.clone());
+        }
+        self.temporaries.insert(register);
+        Expr::atom(format!("r{register}"))
+    }
+
+    /// Reads a register or constant operand.
+    fn read_rk(&mut self, pc: usize, index: usize) -> Expr {
+        let Some(arg) = self
+            .instruction(pc)
+            .and_then(|instruction| instruction.args.get(index))
+        else {
+            return Expr::constant(None);
+        };
+        match arg.mode {
+            HSOpArgMode::CONST => self.constant(arg.value),
+            _ => self.read(usize::try_from(arg.value).unwrap_or_default(), pc),
+        }
+    }
+
+    /// Reads the registers from `start` up to `end`, or up to the top of the stack if `end` is `None`.
+    fn read_list(&mut self, start: usize, end: Option<usize>, pc: usize) -> Vec<Expr> {
+        let end = end.unwrap_or_else(|| self.multi.map_or(start, |multi| multi + 1));
+        let mut list = Vec::new();
+        let mut register = start;
+        while register < end {
+            let expr = self.read(register, pc);
+            register += 1;
+            if let ExprKind::Multi(count) = expr.kind {
+                let covered = count.map_or(end, |count| register - 1 + count as usize);
+                while register < covered.min(end) {
+                    self.pending.remove(&register);
+                    register += 1;
+                }
+            }
+            list.push(expr);
+        }
+        list
+    }
+
+    fn table(items: &[String]) -> Expr {
+        if items.is_empty() {
+            Expr::atom("{}")
+        } else {
+            Expr::atom(format!("{{ {} }}", items.join(", ")))
+        }
+    }
+
+    /// Formats an assignment, using the `function name()` syntax for closures.
+    fn assignment(target: &str, value: &Expr) -> String {
+        let is_path = target
+            .split('.')
+            .all(|part| is_name(part) || part.is_empty())
+            && !target.starts_with('.');
+        if value.kind == ExprKind::Function && is_path {
+            value
+                .text
+                .replacen("function(", &format!("function {target}("), 1)
+        } else {
+            format!("{target} = {}", value.text)
+        }
+    }
+
+    /// Stores a value into a register, either as a pending value or as an assignment to a local.
+    fn assign(&mut self, register: usize, expr: Expr, pc: usize, out: &mut Vec<Statement>) {
+        if let Some(old) = self.pending.remove(&register) {
+            self.flush(register, old, out);
+        }
+        if self.capture != Some(register)
+            && let Some(local) = self.active_local(register, pc)
+        {
+            let statement = Self::assignment(&self.locals[local].name.clone(), &expr);
+            self.emit(Statement::Line(statement), out);
+            return;
+        }
+        self.sequence += 1;
+        self.pending.insert(
+            register,
+            Pending {
+                expr,
+                sequence: self.sequence,
+            },
+        );
+    }
+
+    /// Stores a value spanning `count` registers.
+    fn assign_multi(
+        &mut self,
+        register: usize,
+        count: u32,
+        expr: Expr,
+        pc: usize,
+        out: &mut Vec<Statement>,
+    ) {
+        let registers = register..register + count as usize;
+        if registers
+            .clone()
+            .any(|register| self.active_local(register, pc).is_some())
+        {
+            let targets = registers
+                .map(|register| self.target_name(register, pc))
+                .collect::<Vec<_>>();
+            self.emit(
+                Statement::Line(format!("{} = {}", targets.join(", "), expr.text)),
+                out,
+            );
+            return;
+        }
+        self.assign(register, expr, pc, out);
+        for continuation in register + 1..register + count as usize {
+            let expr = Expr::new(format!("r{continuation}"), ATOM, ExprKind::Continuation);
+            self.assign(continuation, expr, pc, out);
+        }
+    }
+
+    fn target_name(&mut self, register: usize, pc: usize) -> String {
+        if let Some(local) = self.active_local(register, pc) {
+            return self.locals[local].name.clone();
+        }
+        self.temporaries.insert(register);
+        format!("r{register}")
+    }
+
+    /// Turns a pending value that is no longer going to be inlined into a statement.
+    fn flush(&mut self, register: usize, pending: Pending, out: &mut Vec<Statement>) {
+        let statement = match pending.expr.kind {
+            ExprKind::Call => pending.expr.text,
+            ExprKind::Continuation | ExprKind::SelfArg => return,
+            ExprKind::Multi(count) => {
+                let count = count.unwrap_or(1).max(1) as usize;
+                let targets = (register..register + count)
+                    .map(|register| {
+                        self.temporaries.insert(register);
+                        format!("r{register}")
+                    })
+                    .collect::<Vec<_>>();
+                format!("{} = {}", targets.join(", "), pending.expr.text)
+            }
+            ExprKind::Table(items) => {
+                self.temporaries.insert(register);
+                format!("r{register} = {}", Self::table(&items).text)
+            }
+            _ => {
+                self.temporaries.insert(register);
+                Self::assignment(&format!("r{register}"), &pending.expr)
+            }
+        };
+        if self.multi == Some(register) {
+            self.multi = None;
+        }
+        out.push(Statement::Line(statement));
+    }
+
+    /// Flushes every pending value, in the order they were created.
+    fn flush_all(&mut self, out: &mut Vec<Statement>) {
+        let mut pending = std::mem::take(&mut self.pending)
+            .into_iter()
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|(_, pending)| pending.sequence);
+        for (register, pending) in pending {
+            self.flush(register, pending, out);
+        }
+        self.multi = None;
+    }
+
+    /// Emits a statement, flushing pending calls first so that side effects stay in order.
+    fn emit(&mut self, statement: Statement, out: &mut Vec<Statement>) {
+        let mut calls = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| matches!(pending.expr.kind, ExprKind::Call | ExprKind::Multi(_)))
+            .map(|(register, pending)| (*register, pending.sequence))
+            .collect::<Vec<_>>();
+        calls.sort_by_key(|(_, sequence)| *sequence);
+        for (register, _) in calls {
+            if let Some(pending) = self.pending.remove(&register) {
+                self.flush(register, pending, out);
+            }
+        }
+        out.push(statement);
+    }
+
+    /// Declares the locals that come into scope at `pc`.
+    fn declare_locals(&mut self, pc: usize, out: &mut Vec<Statement>) {
+        let starting = (0..self.locals.len())
+            .filter(|local| {
+                let local = &self.locals[*local];
+                local.start == pc && !local.declared && !local.name.starts_with('(')
+            })
+            .collect::<Vec<_>>();
+        if starting.is_empty() {
+            return;
+        }
+
+        let mut names = Vec::new();
+        let mut values = Vec::new();
+        for local in starting {
+            self.locals[local].declared = true;
+            names.push(self.locals[local].name.clone());
+            let register = self.locals[local].register;
+            if let Some(pending) = self.pending.remove(&register) {
+                if self.multi == Some(register) {
+                    self.multi = None;
+                }
+                match pending.expr.kind {
+                    ExprKind::Continuation => {}
+                    ExprKind::Table(items) => values.push(Some(Self::table(&items))),
+                    _ => values.push(Some(pending.expr)),
+                }
+            } else if self.temporaries.contains(&register) {
+                values.push(Some(Expr::atom(format!("r{register}"))));
+            } else {
+                values.push(None);
+            }
+        }
+        while values
+            .last()
+            .is_some_and(|value| value.as_ref().is_none_or(|value| value.text == "nil"))
+        {
+            values.pop();
+        }
+
+        let statement = match values.as_slice() {
+            [] => format!("local {}", names.join(", ")),
+            [Some(value)] if names.len() == 1 && value.kind == ExprKind::Function => value
+                .text
+                .replacen("function(", &format!("local function {}(", names[0]), 1),
+            _ => {
+                let values = values
+                    .into_iter()
+                    .map(|value| value.map_or_else(|| "nil".to_string(), |value| value.text))
+                    .collect::<Vec<_>>();
+                format!("local {} = {}", names.join(", "), values.join(", "))
+            }
+        };
+        self.emit(Statement::Line(statement), out);
+    }
+
+    /// Decompiles the instructions from `start` up to `end` into `out`.
+    fn block(&mut self, start: usize, end: usize, out: &mut Vec<Statement>) {
+        let mut pc = start;
+        while pc < end {
+            pc = self.statement(pc, end, out).max(pc + 1);
+        }
+    }
+
+    /// Decompiles the statement starting at `pc`, returning where the next one starts.
+    fn statement(&mut self, pc: usize, end: usize, out: &mut Vec<Statement>) -> usize {
+        if let Some(next) = self.try_loop(pc, end, out) {
+            return next;
+        }
+        let next = match self.op(pc) {
+            op if Self::is_condition(op) => self.conditional(pc, end, out),
+            Some(HSOpCode::TestSet) => self.test_set(pc, end, out),
+            Some(HSOpCode::ForPrep) => self.numeric_for(pc, end, out),
+            Some(HSOpCode::Jmp) => self.jump(pc, end, out),
+            _ => pc + self.lift(pc, out),
+        };
+        self.declare_locals(next, out);
+        next
+    }
+
+    /// Decompiles `while` and `repeat` loops, recognized by a jump back to `pc`.
+    fn try_loop(&mut self, pc: usize, end: usize, out: &mut Vec<Statement>) -> Option<usize> {
+        let back_jump = (pc + 1..end).rev().find(|j| {
+            self.op(*j) == Some(HSOpCode::Jmp)
+                && self.jump_target(*j) == Some(pc)
+                && self.op(*j - 1) != Some(HSOpCode::TForLoop)
+        })?;
+        let exit = back_jump + 1;
+        self.flush_all(out);
+        self.loop_exits.push(exit);
+
+        let statement = if Self::is_condition(self.op(back_jump - 1)) {
+            let mut body = Vec::new();
+            self.block(pc, back_jump - 1, &mut body);
+            let condition = self.condition(back_jump - 1);
+            self.flush_all(&mut body);
+            Statement::Repeat {
+                body,
+                condition: condition.to_expr().text,
+            }
+        } else if let Some(chain) = self
+            .scan_chain(pc, back_jump)
+            .filter(|chain| chain.exit == exit)
+        {
+            let condition = self.lift_chain(pc, &chain, out);
+            self.flush_all(out);
+            let mut body = Vec::new();
+            self.block(chain.body, back_jump, &mut body);
+            self.flush_all(&mut body);
+            Statement::While {
+                condition: condition.to_expr().text,
+                body,
+            }
+        } else {
+            let mut body = Vec::new();
+            self.block(pc, back_jump, &mut body);
+            self.flush_all(&mut body);
+            Statement::While {
+                condition: "true".to_string(),
+                body,
+            }
+        };
+
+        self.loop_exits.pop();
+        out.push(statement);
+        self.declare_locals(exit, out);
+        Some(exit)
+    }
+
+    /// Finds the longest chain of conditional jumps starting at `pc` that forms a single condition.
+    fn scan_chain(&self, start: usize, end: usize) -> Option<Chain> {
+        let mut pairs = Vec::new();
+        let mut best = None;
+        let mut pc = start;
+        loop {
+            while pc < end && self.is_pure(pc) {
+                pc += 1;
+            }
+            if pc + 1 >= end
+                || !Self::is_condition(self.op(pc))
+                || self.op(pc + 1) != Some(HSOpCode::Jmp)
+            {
+                break;
+            }
+            let Some(target) = self.jump_target(pc + 1) else {
+                break;
+            };
+            pairs.push((pc, target));
+            let body = pc + 2;
+            let valid = target > pc + 1
+                && pairs[..pairs.len() - 1]
+                    .iter()
+                    .all(|(_, other)| *other == target || *other == body);
+            if valid {
+                best = Some(Chain {
+                    pairs: pairs.clone(),
+                    body,
+                    exit: target,
+                });
+            }
+            pc = body;
+        }
+        best
+    }
+
+    /// Lifts the instructions of a chain, returning the condition under which its body runs.
+    fn lift_chain(&mut self, start: usize, chain: &Chain, out: &mut Vec<Statement>) -> Condition {
+        let mut terms = Vec::new();
+        let mut pc = start;
+        for (index, (condition_pc, target)) in chain.pairs.iter().enumerate() {
+            while pc < *condition_pc {
+                pc += self.lift(pc, out);
+            }
+            let mut term = self.condition(*condition_pc);
+            let is_last = index + 1 == chain.pairs.len();
+            if !is_last && *target == chain.body {
+                term = term.negate();
+            }
+            // Jumping to the exit means every following term must hold, so they are joined with `and`.
+            terms.push((term, *target == chain.exit));
+            pc = condition_pc + 2;
+        }
+
+        let (mut condition, _) = terms.pop().expect("chains have at least one condition");
+        while let Some((term, is_and)) = terms.pop() {
+            condition = Condition::Logical(Box::new(term), is_and, Box::new(condition));
+        }
+        condition
+    }
+
+    /// Returns the condition under which the jump following the instruction at `pc` is skipped.
+    fn condition(&mut self, pc: usize) -> Condition {
+        let negated = self.arg(pc, 0) != 0;
+        let (operator, negated_operator) = match self.op(pc) {
+            Some(HSOpCode::Eq | HSOpCode::EqBk) => ("==", "~="),
+            Some(HSOpCode::Lt | HSOpCode::LtBk) => ("<", ">="),
+            Some(HSOpCode::Le | HSOpCode::LeBk) => ("<=", ">"),
+            _ => {
+                let value = self.read(self.reg(pc, 0), pc);
+                return Condition::Test(value, self.arg(pc, 1) != 0);
+            }
+        };
+        let left = self.read_rk(pc, 1);
+        let right = self.read_rk(pc, 2);
+        let operator = if negated { negated_operator } else { operator };
+        Condition::Compare(left, operator, right)
+    }
+
+    /// Decompiles a conditional instruction followed by a jump.
+    fn conditional(&mut self, pc: usize, end: usize, out: &mut Vec<Statement>) -> usize {
+        // `x = a < b` materializes the result of a comparison with a pair of `LoadBool`s.
+        if pc + 3 < end
+            && self.op(pc + 1) == Some(HSOpCode::Jmp)
+            && self.jump_target(pc + 1) == Some(pc + 3)
+            && self.op(pc + 2) == Some(HSOpCode::LoadBool)
+            && self.op(pc + 3) == Some(HSOpCode::LoadBool)
+            && self.reg(pc + 2, 0) == self.reg(pc + 3, 0)
+            && self.arg(pc + 2, 2) != 0
+        {
+            let condition = self.condition(pc);
+            let condition = if self.arg(pc + 3, 1) != 0 {
+                condition.negate()
+            } else {
+                condition
+            };
+            self.assign(self.reg(pc + 3, 0), condition.to_expr(), pc + 3, out);
+            return pc + 4;
+        }
+
+        if let Some(chain) = self.scan_chain(pc, end).filter(|chain| chain.exit <= end) {
+            return self.if_statement(pc, &chain, end, out);
+        }
+
+        if self.op(pc + 1) != Some(HSOpCode::Jmp) || pc + 1 >= end {
+            self.comment(pc, out);
+            return pc + 1;
+        }
+        // The jump leaves the current block, which is either a `break` or something that can't be structured.
+        let target = self.jump_target(pc + 1);
+        let condition = self.condition(pc).negate();
+        self.flush_all(out);
+        let then = if target.is_some() && target == self.loop_exits.last().copied() {
+            Statement::Line("break".to_string())
+        } else {
+            Statement::Line(format!(
+                "-- jump to instruction {}",
+                target.unwrap_or_default()
+            ))
+        };
+        out.push(Statement::If {
+            condition: condition.to_expr().text,
+            then: vec![then],
+            otherwise: Vec::new(),
+        });
+        pc + 2
+    }
+
+    fn if_statement(
+        &mut self,
+        pc: usize,
+        chain: &Chain,
+        end: usize,
+        out: &mut Vec<Statement>,
+    ) -> usize {
+        let condition = self.lift_chain(pc, chain, out);
+        self.flush_all(out);
+
+        // A forward jump at the end of the `then` block skips over an `else` block.
+        let exit = chain.exit;
+        let otherwise_end = Some(exit - 1)
+            .filter(|jump| *jump >= chain.body && self.op(*jump) == Some(HSOpCode::Jmp))
+            .and_then(|jump| self.jump_target(jump))
+            .filter(|target| *target > exit && *target <= end);
+
+        let mut then = Vec::new();
+        let then_end = if otherwise_end.is_some() {
+            exit - 1
+        } else {
+            exit
+        };
+        self.block(chain.body, then_end, &mut then);
+        self.flush_all(&mut then);
+
+        let mut otherwise = Vec::new();
+        if let Some(otherwise_end) = otherwise_end {
+            self.block(exit, otherwise_end, &mut otherwise);
+            self.flush_all(&mut otherwise);
+        }
+
+        out.push(Statement::If {
+            condition: condition.to_expr().text,
+            then,
+            otherwise,
+        });
+        otherwise_end.unwrap_or(exit)
+    }
+
+    /// Decompiles `a and b` and `a or b`, which use `TestSet` when assigned to an existing register.
+    fn test_set(&mut self, pc: usize, end: usize, out: &mut Vec<Statement>) -> usize {
+        let target = Some(pc + 1)
+            .filter(|jump| self.op(*jump) == Some(HSOpCode::Jmp))
+            .and_then(|jump| self.jump_target(jump))
+            .filter(|target| *target > pc + 1 && *target <= end);
+        let Some(target) = target else {
+            self.comment(pc, out);
+            return pc + 1;
+        };
+
+        let register = self.reg(pc, 0);
+        let left = self.read(self.reg(pc, 1), pc);
+        let operator = if self.arg(pc, 2) == 0 { "and" } else { "or" };
+        let precedence = if operator == "and" { AND } else { OR };
+
+        // The last instruction stores the right operand, which may be a local.
+        let is_expression = (pc + 2..target).all(|inner| {
+            if inner + 1 == target {
+                self.computes_value(inner) && self.reg(inner, 0) == register
+            } else {
+                self.is_pure(inner)
+            }
+        });
+        if is_expression {
+            self.capture = Some(register);
+            let mut inner = pc + 2;
+            while inner < target {
+                inner += self.lift(inner, out);
+            }
+            self.capture = None;
+            let right = self.read(register, target - 1);
+            let value = Expr::binary(&left, operator, &right, precedence);
+            self.assign(register, value, target - 1, out);
+            return target;
+        }
+
+        // Fall back to an `if` statement assigning the register.
+        let condition = Condition::Test(left.clone(), operator == "or");
+        self.assign(register, left, pc, out);
+        self.flush_all(out);
+        let mut then = Vec::new();
+        self.block(pc + 2, target, &mut then);
+        self.flush_all(&mut then);
+        out.push(Statement::If {
+            condition: condition.to_expr().text,
+            then,
+            otherwise: Vec::new(),
+        });
+        target
+    }
+
+    fn numeric_for(&mut self, pc: usize, end: usize, out: &mut Vec<Statement>) -> usize {
+        let Some(loop_pc) = self
+            .jump_target(pc)
+            .filter(|target| *target < end && self.op(*target) == Some(HSOpCode::ForLoop))
+        else {
+            self.comment(pc, out);
+            return pc + 1;
+        };
+
+        let base = self.reg(pc, 0);
+        let init = self.read(base, pc);
+        let limit = self.read(base + 1, pc);
+        let step = self.read(base + 2, pc);
+        self.flush_all(out);
+
+        let variable = self.loop_variable(base + 3, pc + 1);
+        let mut header = format!("{variable} = {}, {}", init.text, limit.text);
+        if step.text != "1" {
+            header = format!("{header}, {}", step.text);
+        }
+
+        let exit = loop_pc + 1;
+        self.loop_exits.push(exit);
+        let mut body = Vec::new();
+        self.block(pc + 1, loop_pc, &mut body);
+        self.flush_all(&mut body);
+        self.loop_exits.pop();
+
+        out.push(Statement::For { header, body });
+        exit
+    }
+
+    /// Names the variable a loop binds to `register`, marking its local as declared.
+    fn loop_variable(&mut self, register: usize, pc: usize) -> String {
+        match self.active_local(register, pc) {
+            Some(local) => {
+                self.locals[local].declared = true;
+                self.locals[local].name.clone()
+            }
+            None => format!("r{register}"),
+        }
+    }
+
+    fn jump(&mut self, pc: usize, end: usize, out: &mut Vec<Statement>) -> usize {
+        let Some(target) = self.jump_target(pc) else {
+            self.comment(pc, out);
+            return pc + 1;
+        };
+
+        // Generic `for` loops jump to their `TForLoop`, which is followed by a jump back to the body.
+        if target > pc
+            && target + 1 < end
+            && self.op(target) == Some(HSOpCode::TForLoop)
+            && self.op(target + 1) == Some(HSOpCode::Jmp)
+            && self.jump_target(target + 1) == Some(pc + 1)
+        {
+            let base = self.reg(target, 0);
+            let variable_count = usize::try_from(self.arg(target, 1)).unwrap_or_default();
+            let mut expressions = self.read_list(base, Some(base + 3), pc);
+            while expressions
+                .last()
+                .is_some_and(|expr| expr.text == "nil" && expressions.len() > 1)
+            {
+                expressions.pop();
+            }
+            self.flush_all(out);
+
+            let variables = (0..variable_count.max(1))
+                .map(|index| self.loop_variable(base + 3 + index, pc + 1))
+                .collect::<Vec<_>>();
+            let header = format!(
+                "{} in {}",
+                variables.join(", "),
+                expressions
+                    .iter()
+                    .map(|expr| expr.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            let exit = target + 2;
+            self.loop_exits.push(exit);
+            let mut body = Vec::new();
+            self.block(pc + 1, target, &mut body);
+            self.flush_all(&mut body);
+            self.loop_exits.pop();
+
+            out.push(Statement::For { header, body });
+            return exit;
+        }
+
+        if target != pc + 1 {
+            self.flush_all(out);
+            if Some(target) == self.loop_exits.last().copied() {
+                out.push(Statement::Line("break".to_string()));
+            } else {
+                out.push(Statement::Line(format!("-- jump to instruction {target}")));
+            }
+        }
+        pc + 1
+    }
+
+    /// Emits an instruction that couldn't be decompiled as a comment.
+    fn comment(&mut self, pc: usize, out: &mut Vec<Statement>) {
+        let Some(instruction) = self.instruction(pc) else {
+            return;
+        };
+        let line = if instruction.mode == HSOpCode::Unknown {
+            format!("-- Unknown({:#010x})", instruction.raw)
+        } else {
+            let args = instruction
+                .args
+                .iter()
+                .map(|arg| format!("{}({})", arg.mode, arg.value))
+                .collect::<Vec<_>>();
+            format!("-- {} {}", instruction.mode, args.join(" "))
+        };
+        self.flush_all(out);
+        out.push(Statement::Line(line));
+    }
+
+    /// Lifts a single instruction that doesn't affect control flow, returning how many instructions it used.
+    #[allow(clippy::too_many_lines)]
+    fn lift(&mut self, pc: usize, out: &mut Vec<Statement>) -> usize {
+        let Some(op) = self.op(pc) else {
+            return 1;
+        };
+        let a = self.reg(pc, 0);
+        match op {
+            HSOpCode::Move => {
+                let value = self.read(self.reg(pc, 1), pc);
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::LoadK => {
+                let value = self.constant(self.arg(pc, 1));
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::LoadBool => {
+                let value = Expr::new((self.arg(pc, 1) != 0).to_string(), ATOM, ExprKind::Literal);
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::LoadNil => {
+                for register in a..=self.reg(pc, 1).max(a) {
+                    self.assign(register, Expr::new("nil", ATOM, ExprKind::Literal), pc, out);
+                }
+            }
+            HSOpCode::GetUpval => {
+                let value = Expr::atom(self.up_value(self.arg(pc, 1)));
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::GetGlobal | HSOpCode::GetGlobalMem => {
+                let value = self.global(self.arg(pc, 1));
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::GetField
+            | HSOpCode::GetFieldR1
+            | HSOpCode::GetFieldMm
+            | HSOpCode::GetTable
+            | HSOpCode::GetTableS
+            | HSOpCode::GetTableN => {
+                let object = self.read(self.reg(pc, 1), pc);
+                let key = self.read_rk(pc, 2);
+                self.assign(a, Expr::index(&object, &key), pc, out);
+            }
+            HSOpCode::SetGlobal => {
+                let value = self.read(a, pc);
+                let name = self.global(self.arg(pc, 1));
+                self.emit(Statement::Line(Self::assignment(&name.text, &value)), out);
+            }
+            HSOpCode::SetUpval | HSOpCode::SetUpvalR1 => {
+                let value = self.read(a, pc);
+                let name = self.up_value(self.arg(pc, 1));
+                self.emit(Statement::Line(Self::assignment(&name, &value)), out);
+            }
+            HSOpCode::SetField
+            | HSOpCode::SetFieldR1
+            | HSOpCode::SetTable
+            | HSOpCode::SetTableBk
+            | HSOpCode::SetTableS
+            | HSOpCode::SetTableSBk
+            | HSOpCode::SetTableN
+            | HSOpCode::SetTableNBk => {
+                let value = self.read_rk(pc, 2);
+                let key = self.read_rk(pc, 1);
+                self.set_index(a, &key, &value, pc, out);
+            }
+            HSOpCode::Add | HSOpCode::AddBk => self.arithmetic(pc, "+", ADD, out),
+            HSOpCode::Sub | HSOpCode::SubBk => self.arithmetic(pc, "-", ADD, out),
+            HSOpCode::Mul | HSOpCode::MulBk => self.arithmetic(pc, "*", MUL, out),
+            HSOpCode::Div | HSOpCode::DivBk => self.arithmetic(pc, "/", MUL, out),
+            HSOpCode::Mod | HSOpCode::ModBk => self.arithmetic(pc, "%", MUL, out),
+            HSOpCode::Pow | HSOpCode::PowBk => self.arithmetic(pc, "^", POW, out),
+            HSOpCode::Unm | HSOpCode::Not | HSOpCode::NotR1 | HSOpCode::Len => {
+                let operator = match op {
+                    HSOpCode::Unm => "-",
+                    HSOpCode::Len => "#",
+                    _ => "not",
+                };
+                let operand = self.read(self.reg(pc, 1), pc);
+                self.assign(a, Expr::unary(operator, &operand), pc, out);
+            }
+            HSOpCode::Concat => {
+                let first = self.reg(pc, 1);
+                let last = self.reg(pc, 2).max(first);
+                let mut operands = (first..=last)
+                    .map(|register| self.read(register, pc))
+                    .collect::<Vec<_>>();
+                let mut value = operands.pop().unwrap_or_else(|| Expr::atom("\"\""));
+                while let Some(operand) = operands.pop() {
+                    value = Expr::binary(&operand, "..", &value, CONCAT);
+                }
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::NewTable => {
+                let value = Expr::new("{}", ATOM, ExprKind::Table(Vec::new()));
+                self.assign(a, value, pc, out);
+            }
+            HSOpCode::SetList => {
+                let count = self.reg(pc, 1);
+                let end = (count != 0).then_some(a + 1 + count);
+                let items = self.read_list(a + 1, end, pc);
+                self.set_list(a, items, pc, out);
+            }
+            HSOpCode::SelfOp => {
+                let object = self.read(self.reg(pc, 1), pc);
+                let key = self.read_rk(pc, 2);
+                self.method(a, &object, key, pc, out);
+            }
+            HSOpCode::Call
+            | HSOpCode::CallI
+            | HSOpCode::CallC
+            | HSOpCode::CallM
+            | HSOpCode::CallIR1 => self.call(pc, false, out),
+            HSOpCode::TailCall
+            | HSOpCode::TailCallI
+            | HSOpCode::TailCallC
+            | HSOpCode::TailCallM
+            | HSOpCode::TailCallIR1 => self.call(pc, true, out),
+            HSOpCode::Return => {
+                let count = self.reg(pc, 1);
+                if count == 1 {
+                    if pc + 1 != self.function.instructions.len() {
+                        self.emit(Statement::Line("return".to_string()), out);
+                    }
+                } else {
+                    let end = (count != 0).then(|| a + count - 1);
+                    let values = self.read_list(a, end, pc);
+                    let values = values
+                        .iter()
+                        .map(|value| value.text.as_str())
+                        .collect::<Vec<_>>();
+                    self.emit(
+                        Statement::Line(format!("return {}", values.join(", "))),
+                        out,
+                    );
+                }
+            }
+            HSOpCode::Vararg => {
+                let count = self.arg(pc, 1);
+                match u32::try_from(count - 1) {
+                    Err(_) => {
+                        let value = Expr::new("...", ATOM, ExprKind::Multi(None));
+                        self.assign(a, value, pc, out);
+                        self.multi = Some(a);
+                    }
+                    Ok(0 | 1) => self.assign(a, Expr::atom("..."), pc, out),
+                    Ok(count) => {
+                        let value = Expr::new("...", ATOM, ExprKind::Multi(Some(count)));
+                        self.assign_multi(a, count, value, pc, out);
+                    }
+                }
+            }
+            HSOpCode::Closure => return self.closure(pc, out),
+            HSOpCode::Close
+            | HSOpCode::Data
+            | HSOpCode::CheckType
+            | HSOpCode::CheckTypes
+            | HSOpCode::CheckTypeD => {}
+            HSOpCode::NewStruct => {
+                let id = self.function.new_struct_id(pc);
+                let value = match self
+                    .structs
+                    .iter()
+                    .find(|structure| Some(structure.id) == id)
+                {
+                    Some(structure) => format!("hmake {} {{}}", structure.name),
+                    None => "hmake {}".to_string(),
+                };
+                // Like a table constructor, `hmake` needs parentheses to be indexed.
+                self.assign(a, Expr::new(value, ATOM, ExprKind::Literal), pc, out);
+            }
+            HSOpCode::GetSlot | HSOpCode::GetSlotMt | HSOpCode::GetSlotD => {
+                let object = self.read(self.reg(pc, 1), pc);
+                self.assign(a, Self::slot(&object, self.arg(pc, 2)), pc, out);
+            }
+            HSOpCode::SelfSlot | HSOpCode::SelfSlotMt => {
+                let object = self.read(self.reg(pc, 1), pc);
+                let key = Expr::new(
+                    format!("\"slot{}\"", self.arg(pc, 2)),
+                    ATOM,
+                    ExprKind::Literal,
+                );
+                self.method(a, &object, key, pc, out);
+            }
+            HSOpCode::SetSlot | HSOpCode::SetSlotI | HSOpCode::SetSlotS | HSOpCode::SetSlotMt => {
+                let value = self.read_rk(pc, 2);
+                let object = self.read(a, pc);
+                let target = Self::slot(&object, self.arg(pc, 1));
+                self.emit(Statement::Line(Self::assignment(&target.text, &value)), out);
+            }
+            HSOpCode::SetSlotN => {
+                let object = self.read(a, pc);
+                let target = Self::slot(&object, self.arg(pc, 1));
+                self.emit(Statement::Line(format!("{} = nil", target.text)), out);
+            }
+            _ => self.comment(pc, out),
+        }
+        1
+    }
+
+    fn arithmetic(&mut self, pc: usize, operator: &str, precedence: u8, out: &mut Vec<Statement>) {
+        let left = self.read_rk(pc, 1);
+        let right = self.read_rk(pc, 2);
+        let value = Expr::binary(&left, operator, &right, precedence);
+        self.assign(self.reg(pc, 0), value, pc, out);
+    }
+
+    fn set_index(
+        &mut self,
+        register: usize,
+        key: &Expr,
+        value: &Expr,
+        pc: usize,
+        out: &mut Vec<Statement>,
+    ) {
+        if let Some(Pending {
+            expr:
+                Expr {
+                    kind: ExprKind::Table(items),
+                    ..
+                },
+            ..
+        }) = self.pending.get_mut(&register)
+        {
+            match key.as_name() {
+                Some(name) => items.push(format!("{name} = {}", value.text)),
+                None => items.push(format!("[{}] = {}", key.text, value.text)),
+            }
+            return;
+        }
+        let object = self.read(register, pc);
+        let target = Expr::index(&object, key);
+        self.emit(Statement::Line(Self::assignment(&target.text, value)), out);
+    }
+
+    fn set_list(&mut self, register: usize, items: Vec<Expr>, pc: usize, out: &mut Vec<Statement>) {
+        if let Some(Pending {
+            expr:
+                Expr {
+                    kind: ExprKind::Table(table),
+                    ..
+                },
+            ..
+        }) = self.pending.get_mut(&register)
+        {
+            table.extend(items.into_iter().map(|item| item.text));
+            return;
+        }
+        let batch = self
+            .function
+            .set_list_batch(pc)
+            .unwrap_or_default()
+            .saturating_sub(1);
+        let object = self.read(register, pc);
+        for (index, item) in items.into_iter().enumerate() {
+            let position = batch * FIELDS_PER_FLUSH + index + 1;
+            self.emit(
+                Statement::Line(format!("{}[{position}] = {}", object.prefix(), item.text)),
+                out,
+            );
+        }
+    }
+
+    fn method(
+        &mut self,
+        register: usize,
+        object: &Expr,
+        key: Expr,
+        pc: usize,
+        out: &mut Vec<Statement>,
+    ) {
+        self.assign(
+            register + 1,
+            Expr::new(object.text.clone(), object.precedence, ExprKind::SelfArg),
+            pc,
+            out,
+        );
+        let text = Expr::index(object, &key).text;
+        let value = Expr::new(text, ATOM, ExprKind::Method(object.prefix(), Box::new(key)));
+        self.assign(register, value, pc, out);
+    }
+
+    fn call(&mut self, pc: usize, is_tail_call: bool, out: &mut Vec<Statement>) {
+        let base = self.reg(pc, 0);
+        let arg_count = self.reg(pc, 1);
+        let result_count = self.arg(pc, 2);
+
+        let function = self.read(base, pc);
+        let mut first_arg = base + 1;
+        let callee = match &function.kind {
+            ExprKind::Method(object, key)
+                if key.as_name().is_some()
+                    && self
+                        .pending
+                        .get(&first_arg)
+                        .is_some_and(|pending| pending.expr.kind == ExprKind::SelfArg) =>
+            {
+                self.pending.remove(&first_arg);
+                first_arg += 1;
+                format!("{object}:{}", key.as_name().unwrap_or_default())
+            }
+            _ => function.prefix(),
+        };
+        let end = (arg_count != 0).then_some(base + arg_count);
+        let args = self.read_list(first_arg, end, pc);
+        let args = args.iter().map(|arg| arg.text.as_str()).collect::<Vec<_>>();
+        let text = format!("{callee}({})", args.join(", "));
+
+        if is_tail_call {
+            self.emit(Statement::Line(format!("return {text}")), out);
+            return;
+        }
+        match u32::try_from(result_count - 1) {
+            Err(_) => {
+                self.assign(base, Expr::new(text, ATOM, ExprKind::Multi(None)), pc, out);
+                self.multi = Some(base);
+            }
+            Ok(0) => self.emit(Statement::Line(text), out),
+            Ok(1) => self.assign(base, Expr::new(text, ATOM, ExprKind::Call), pc, out),
+            Ok(count) => {
+                let value = Expr::new(text, ATOM, ExprKind::Multi(Some(count)));
+                self.assign_multi(base, count, value, pc, out);
+            }
+        }
+    }
+
+    fn closure(&mut self, pc: usize, out: &mut Vec<Statement>) -> usize {
+        let Some((_, child)) = self.function.closure_child(pc) else {
+            self.comment(pc, out);
+            return 1;
+        };
+        let text = FunctionDecompiler::new(child, self.structs).decompile_closure();
+        self.assign(
+            self.reg(pc, 0),
+            Expr::new(text, ATOM, ExprKind::Function),
+            pc,
+            out,
+        );
+        // The instructions following a closure describe its upvalues and are never executed.
+        let remaining = self.function.instructions.len() - pc - 1;
+        1 + (child.up_value_count as usize).min(remaining)
+    }
+}
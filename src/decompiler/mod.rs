@@ -0,0 +1,48 @@
+//! Decompiles `HavokScript` bytecode back into Lua source.
+//!
+//! The output is meant to be read rather than recompiled: constructs that can't be
+//! structured (such as stray jumps or unknown extension opcodes) are kept as comments.
+
+pub mod expression;
+pub mod function;
+pub mod statement;
+
+use crate::loader::{
+    hs::HavokScriptFile, hs_function::HSFunction, hs_structure::HSStructPrototype,
+};
+use function::FunctionDecompiler;
+
+use std::fmt::{Display, Formatter};
+
+/// Lua source view of a `HavokScriptFile`.
+pub struct HSDecompiler<'a> {
+    /// The function to decompile, along with its children.
+    function: &'a HSFunction,
+    /// Structure prototypes of the file, which the structures that `hmake` creates are named after.
+    structs: &'a [HSStructPrototype],
+}
+
+impl<'a> HSDecompiler<'a> {
+    #[must_use]
+    pub fn new(file: &'a HavokScriptFile) -> Self {
+        Self::for_function(&file.main_function, &file.structs)
+    }
+
+    /// Decompiles a single function and its children, naming structures after `structs`.
+    /// Functions other than the main function are written as a closure expression.
+    #[must_use]
+    pub fn for_function(function: &'a HSFunction, structs: &'a [HSStructPrototype]) -> Self {
+        Self { function, structs }
+    }
+}
+
+impl Display for HSDecompiler<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let decompiler = FunctionDecompiler::new(self.function, self.structs);
+        if self.function.depth == 0 {
+            write!(f, "{}", decompiler.decompile(0))
+        } else {
+            writeln!(f, "{}", decompiler.decompile_closure())
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! Lua statements produced by the decompiler and their rendering.
+
+use std::fmt::Write;
+
+/// Indentation used for each nested block.
+const INDENT: &str = "    ";
+
+/// A Lua statement.
+pub enum Statement {
+    /// A statement that fits on one line, or an expression that renders its own lines.
+    Line(String),
+    /// `if` statement with an optional `else` block.
+    If {
+        condition: String,
+        then: Vec<Statement>,
+        otherwise: Vec<Statement>,
+    },
+    /// `while` loop.
+    While {
+        condition: String,
+        body: Vec<Statement>,
+    },
+    /// `repeat ... until` loop.
+    Repeat {
+        body: Vec<Statement>,
+        condition: String,
+    },
+    /// Numeric or generic `for` loop, holding everything between `for` and `do`.
+    For {
+        header: String,
+        body: Vec<Statement>,
+    },
+}
+
+/// Renders statements at the given indentation level.
+pub fn render(statements: &[Statement], depth: usize, out: &mut String) {
+    for statement in statements {
+        render_statement(statement, depth, out);
+    }
+}
+
+fn line(depth: usize, text: &str, out: &mut String) {
+    // Multi-line statements (such as closures) are already indented relative to their first line.
+    for line in text.lines() {
+        let _ = writeln!(out, "{}{line}", INDENT.repeat(depth));
+    }
+}
+
+fn render_statement(statement: &Statement, depth: usize, out: &mut String) {
+    match statement {
+        Statement::Line(text) => line(depth, text, out),
+        Statement::If {
+            condition,
+            then,
+            otherwise,
+        } => {
+            line(depth, &format!("if {condition} then"), out);
+            render(then, depth + 1, out);
+            let mut otherwise = otherwise;
+            loop {
+                match otherwise.as_slice() {
+                    [] => break,
+                    [
+                        Statement::If {
+                            condition,
+                            then,
+                            otherwise: next,
+                        },
+                    ] => {
+                        line(depth, &format!("elseif {condition} then"), out);
+                        render(then, depth + 1, out);
+                        otherwise = next;
+                    }
+                    statements => {
+                        line(depth, "else", out);
+                        render(statements, depth + 1, out);
+                        break;
+                    }
+                }
+            }
+            line(depth, "end", out);
+        }
+        Statement::While { condition, body } => {
+            line(depth, &format!("while {condition} do"), out);
+            render(body, depth + 1, out);
+            line(depth, "end", out);
+        }
+        Statement::Repeat { body, condition } => {
+            line(depth, "repeat", out);
+            render(body, depth + 1, out);
+            line(depth, &format!("until {condition}"), out);
+        }
+        Statement::For { header, body } => {
+            line(depth, &format!("for {header} do"), out);
+            render(body, depth + 1, out);
+            line(depth, "end", out);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Writes structure prototypes as `EmmyLua` annotations, understood by the Lua language server.
+//!
+//! Every structure becomes a `---@class` inheriting from the structures it extends, with a
+//! `---@field` for every slot. Slots holding a structure are typed with that structure's class.
+//! Slots whose names are Lua keywords or not valid identifiers are written as quoted keys, such
+//! as `---@field ["end"] number`.
+
+use super::slot_structure;
+use crate::loader::{hs::HavokScriptFile, hs_opcodes::HSType, hs_structure::HSStructPrototype};
+
+use std::fmt::{Display, Formatter};
+
+/// Reserved words of Lua, which can't name a field in `a.b` syntax.
+const KEYWORDS: [&str; 22] = [
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// `EmmyLua` `---@class` stubs of the structure prototypes of a `HavokScriptFile`.
+pub struct HSLuaStubs<'a> {
+    /// The structure prototypes to write.
+    structs: &'a [HSStructPrototype],
+}
+
+impl<'a> HSLuaStubs<'a> {
+    #[must_use]
+    pub fn new(file: &'a HavokScriptFile) -> Self {
+        Self::for_structs(&file.structs)
+    }
+
+    /// Writes stubs of the given structure prototypes, which slots holding structures are
+    /// also resolved with.
+    #[must_use]
+    pub fn for_structs(structs: &'a [HSStructPrototype]) -> Self {
+        Self { structs }
+    }
+
+    /// Writes the annotation name of a field, `name` itself when it is a valid Lua identifier,
+    /// and a quoted key otherwise.
+    fn fmt_field_name(f: &mut Formatter<'_>, name: &str) -> std::fmt::Result {
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !KEYWORDS.contains(&name);
+        if is_identifier {
+            return write!(f, "{name}");
+        }
+        write!(f, "[\"")?;
+        for byte in name.bytes() {
+            match byte {
+                b'"' | b'\\' => write!(f, "\\{}", char::from(byte))?,
+                b' '..=b'~' => write!(f, "{}", char::from(byte))?,
+                _ => write!(f, "\\{byte:03}")?,
+            }
+        }
+        write!(f, "\"]")
+    }
+
+    /// Returns the annotation type of a built-in type.
+    fn lua_type(type_: HSType) -> &'static str {
+        match type_ {
+            HSType::TNIL => "nil",
+            HSType::TBOOLEAN => "boolean",
+            HSType::TLIGHTUSERDATA => "lightuserdata",
+            HSType::TNUMBER => "number",
+            HSType::TSTRING => "string",
+            HSType::TTABLE => "table",
+            HSType::TFUNCTION | HSType::TIFUNCTION | HSType::TCFUNCTION => "function",
+            HSType::TUSERDATA => "userdata",
+            HSType::TTHREAD => "thread",
+            HSType::TUI64 => "integer",
+            HSType::TSTRUCT => "any",
+        }
+    }
+}
+
+impl Display for HSLuaStubs<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "---@meta")?;
+        for structure in self.structs {
+            writeln!(f)?;
+            write!(f, "---@class {}", structure.name)?;
+            if !structure.inherited_structs.is_empty() {
+                write!(f, " : {}", structure.inherited_structs.join(", "))?;
+            }
+            writeln!(f)?;
+            for slot in &structure.slots {
+                let type_ = slot_structure(self.structs, slot.type_, slot.struct_id)
+                    .map_or(Self::lua_type(slot.type_), |structure| {
+                        structure.name.as_str()
+                    });
+                write!(f, "---@field ")?;
+                Self::fmt_field_name(f, &slot.name)?;
+                writeln!(f, " {type_}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+//! Writes structure prototypes as a C header for native code bridging with the scripts.
+//!
+//! Every structure gets a `<Name>_ID` define with the id of its prototype, and every slot a
+//! `<Name>_<slot>_OFFSET` define with its offset, commented with its type. Characters that
+//! can't appear in C identifiers are replaced with underscores, names starting with a digit are
+//! prefixed with one, and a define that would repeat an earlier one gets a numeric suffix, such
+//! as `a_b_2_ID` for a structure `a.b` following a structure `a_b`.
+
+use super::slot_structure;
+use crate::loader::{hs::HavokScriptFile, hs_structure::HSStructPrototype};
+
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+};
+
+/// C header with the ids and slot offsets of the structure prototypes of a `HavokScriptFile`.
+pub struct HSCHeader<'a> {
+    /// The structure prototypes to write.
+    structs: &'a [HSStructPrototype],
+}
+
+impl<'a> HSCHeader<'a> {
+    #[must_use]
+    pub fn new(file: &'a HavokScriptFile) -> Self {
+        Self::for_structs(&file.structs)
+    }
+
+    /// Writes a header of the given structure prototypes, which slots holding structures are
+    /// also resolved with.
+    #[must_use]
+    pub fn for_structs(structs: &'a [HSStructPrototype]) -> Self {
+        Self { structs }
+    }
+
+    /// Returns `name` with every character that can't appear in a C identifier replaced, and
+    /// prefixed with an underscore when it would start with a digit.
+    fn identifier(name: &str) -> String {
+        let identifier = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("_{identifier}")
+        } else {
+            identifier
+        }
+    }
+
+    /// Returns `identifier`, or the first of `identifier_2`, `identifier_3` and so on, whose
+    /// define with `suffix` isn't in `defines` yet, and adds that define.
+    fn unique(defines: &mut HashSet<String>, identifier: &str, suffix: &str) -> String {
+        let mut unique = identifier.to_string();
+        let mut count = 1;
+        while !defines.insert(format!("{unique}{suffix}")) {
+            count += 1;
+            unique = format!("{identifier}_{count}");
+        }
+        unique
+    }
+
+    /// Returns `text` with the sequences that would end a comment early broken up.
+    fn comment(text: &str) -> String {
+        text.replace("*/", "* /")
+    }
+}
+
+impl Display for HSCHeader<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#pragma once")?;
+        let mut defines = HashSet::new();
+        for structure in self.structs {
+            let name = Self::unique(&mut defines, &Self::identifier(&structure.name), "_ID");
+            writeln!(f)?;
+            write!(f, "/* {}", Self::comment(&structure.name))?;
+            if !structure.inherited_structs.is_empty() {
+                let parents = structure.inherited_structs.join(", ");
+                write!(f, " : {}", Self::comment(&parents))?;
+            }
+            writeln!(f, " */")?;
+            writeln!(f, "#define {name}_ID {}", structure.id)?;
+            for slot in &structure.slots {
+                let type_ = slot_structure(self.structs, slot.type_, slot.struct_id).map_or_else(
+                    || format!("{:?}", slot.type_),
+                    |structure| format!("struct {}", Self::comment(&structure.name)),
+                );
+                let slot_name = Self::identifier(&format!("{name}_{}", slot.name));
+                let slot_name = Self::unique(&mut defines, &slot_name, "_OFFSET");
+                writeln!(
+                    f,
+                    "#define {slot_name}_OFFSET {} /* {type_} */",
+                    slot.position
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+//! Exports the structure prototypes of a `HavokScriptFile` for tools outside of Lua.
+//!
+//! Structures are engine types that scripts create with `hmake` and access through slots. The
+//! exports keep editor tooling and native code in sync with the prototypes compiled into the
+//! scripts: `emmylua` writes `---@class` stubs for the Lua language server, and `header` writes a
+//! C header with the id of every structure and the offset of every slot.
+
+pub mod emmylua;
+pub mod header;
+
+use crate::loader::{hs_opcodes::HSType, hs_structure::HSStructPrototype};
+
+/// Returns the prototype of the structure held by a slot of type `TSTRUCT`, by its id.
+fn slot_structure(
+    structs: &[HSStructPrototype],
+    type_: HSType,
+    struct_id: u64,
+) -> Option<&HSStructPrototype> {
+    if type_ != HSType::TSTRUCT {
+        return None;
+    }
+    structs.iter().find(|structure| structure.id == struct_id)
+}
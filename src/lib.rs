@@ -0,0 +1,24 @@
+//! `HavokScript` 5.1 bytecode loader.
+//!
+//! The loader can parse scripts from any `Read + Seek` source, such as a `File`,
+//! a `Cursor` or an in-memory byte slice:
+//!
+//! ```no_run
+//! use hkscdis_rs::loader::hs::HavokScriptFile;
+//!
+//! let bytes = std::fs::read("script.luac")?;
+//! let file = HavokScriptFile::from_bytes(&bytes, false)?;
+//! println!("{file}");
+//! # Ok::<(), hkscdis_rs::common::errors::HkscError>(())
+//! ```
+
+#![deny(clippy::pedantic)]
+#![allow(clippy::missing_errors_doc)]
+
+pub mod assembler;
+pub mod cfg;
+pub mod common;
+pub mod decompiler;
+pub mod export;
+pub mod loader;
+pub mod verifier;
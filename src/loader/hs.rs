@@ -1,20 +1,31 @@
 use super::{
+    hs_diagnostics::HSDiagnostic,
+    hs_encoding::HSInstructionEncoding,
     hs_enums::HSEnum,
-    hs_function::HSFunction,
+    hs_function::{HSDisplayContext, HSFunction},
     hs_header::{HSFeatures, HSHeader},
-    hs_reader::read_string,
+    hs_inheritance::{HSInheritance, HSInheritedSlot},
+    hs_options::HSReadOptions,
+    hs_profile::HSOpcodeProfile,
+    hs_reader::{read_int, read_string},
+    hs_source::HSSources,
     hs_structure::HSStructPrototype,
+    hs_writer::{write_int, write_string},
 };
 use crate::{
     common::errors::HkscError,
-    common::extensions::{BufReaderExt, HeaderReadable},
+    common::extensions::{BufReaderExt, WriterExt},
 };
 
-use byteorder::{BE, ByteOrder, LE, ReadBytesExt};
+use byteorder::{BE, ByteOrder, LE};
 use colored::Colorize;
-use std::{fmt::Display, fs::File, io::BufReader};
+use std::{
+    fmt::Display,
+    io::{BufReader, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
+};
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Main container for the Havok Script file.
 pub struct HavokScriptFile {
     /// Header contains information on how to continue reading the file.
@@ -28,48 +39,332 @@ pub struct HavokScriptFile {
     pub main_function: HSFunction,
     /// Havok structure definitions that allow interop with game engine.
     pub structs: Vec<HSStructPrototype>,
+    /// Whether structures use the inheritance extension.
+    pub enable_inheritance: bool,
+    /// Whether `enable_inheritance` was detected from the structures instead of being given
+    /// through `HSReadOptions`.
+    pub inheritance_detected: bool,
+    /// Structures that each structure inherits from, resolved from their names.
+    pub inheritance: HSInheritance,
+    /// Layout of the instruction words, which instructions are written back with.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub encoding: HSInstructionEncoding,
+    /// Opcode profile the instructions were decoded with, which their opcodes are numbered with
+    /// when written back.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub profile: HSOpcodeProfile,
+    /// Problems found while reading with `HSReadOptions::recover`.
+    /// Empty when the whole file was read.
+    pub diagnostics: Vec<HSDiagnostic>,
 }
 
 impl HavokScriptFile {
-    pub fn read(
+    /// Parses a `HavokScript` file from any seekable source.
+    ///
+    /// The source is buffered internally, so its position after this call is unspecified.
+    /// `enable_inheritance` forces the structure layout, use `from_reader_with_options` to detect it.
+    pub fn from_reader<R: Read + Seek>(
+        reader: &mut R,
+        enable_inheritance: bool,
+    ) -> Result<Self, HkscError> {
+        Self::from_reader_with_options(
+            reader,
+            &HSReadOptions {
+                enable_inheritance: Some(enable_inheritance),
+                ..HSReadOptions::default()
+            },
+        )
+    }
+
+    /// Parses a `HavokScript` file from any seekable source, with the given read options.
+    pub fn from_reader_with_options<R: Read + Seek>(
+        reader: &mut R,
+        options: &HSReadOptions,
+    ) -> Result<Self, HkscError> {
+        let mut file = Self::default();
+        file.read(reader, options)?;
+        Ok(file)
+    }
+
+    /// Parses a `HavokScript` file that is already loaded into memory.
+    pub fn from_bytes(bytes: &[u8], enable_inheritance: bool) -> Result<Self, HkscError> {
+        Self::from_reader(&mut Cursor::new(bytes), enable_inheritance)
+    }
+
+    /// Parses a `HavokScript` file that is already loaded into memory, with the given read options.
+    pub fn from_bytes_with_options(
+        bytes: &[u8],
+        options: &HSReadOptions,
+    ) -> Result<Self, HkscError> {
+        Self::from_reader_with_options(&mut Cursor::new(bytes), options)
+    }
+
+    /// Reads the whole file, starting at the current position of `reader`.
+    ///
+    /// With `HSReadOptions::recover`, a failure keeps everything read before it and is
+    /// reported in `diagnostics` instead.
+    pub fn read<R: Read + Seek>(
         &mut self,
-        reader: &mut BufReader<File>,
+        reader: &mut R,
+        options: &HSReadOptions,
+    ) -> Result<(), HkscError> {
+        let reader = &mut BufReader::new(reader);
+        self.profile = options.profile.clone();
+        let result = self
+            .header
+            .read(reader)
+            .map_err(|error| error.within("header", reader))
+            .and_then(|()| {
+                if self.header.is_little_endian {
+                    self.read_body::<LE>(reader, options)
+                } else {
+                    self.read_body::<BE>(reader, options)
+                }
+            });
+        self.main_function.assign_ids("0", 0);
+        self.resolve_inheritance();
+        match result {
+            Err(error) if options.recover => {
+                self.recover(error);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Resolves `inheritance` from the names in the structures' `inherited_structs`, which must
+    /// be called again after changing `structs`.
+    pub fn resolve_inheritance(&mut self) {
+        self.inheritance = HSInheritance::resolve(&self.structs);
+    }
+
+    /// Returns the full slot set of the structure at `index` in `structs`, including the slots it
+    /// inherits along with the structure declaring them.
+    #[must_use]
+    pub fn flattened_slots(&self, index: usize) -> Vec<HSInheritedSlot<'_>> {
+        self.inheritance.flattened_slots(&self.structs, index)
+    }
+
+    /// Records a read failure in `diagnostics`, along with what was read and skipped because of it.
+    fn recover(&mut self, error: HkscError) {
+        let diagnostic = HSDiagnostic::from(error);
+        let section = diagnostic
+            .path
+            .first()
+            .and_then(|segment| segment.split('[').next())
+            .unwrap_or_default()
+            .to_string();
+        let notes = match section.as_str() {
+            "header" => vec!["The header is incomplete, nothing after it was read.".to_string()],
+            "enums" => vec!["The main function and structures were skipped.".to_string()],
+            "main" => {
+                // Every function on the path of the failure is incomplete.
+                let incomplete = 1 + diagnostic
+                    .path
+                    .iter()
+                    .filter(|segment| segment.starts_with("child["))
+                    .count();
+                let read = self.main_function.count_functions();
+                vec![
+                    format!("Functions fully read: {} of {read}.", read - incomplete),
+                    "The structures were skipped.".to_string(),
+                ]
+            }
+            "structs" => vec![format!("Structures fully read: {}.", self.structs.len())],
+            _ => Vec::new(),
+        };
+        self.diagnostics.push(diagnostic);
+        self.diagnostics
+            .extend(notes.into_iter().map(HSDiagnostic::note));
+    }
+
+    /// Reads everything following the header, once its byte order is known.
+    fn read_body<T: ByteOrder>(
+        &mut self,
+        reader: &mut impl BufReaderExt,
+        options: &HSReadOptions,
+    ) -> Result<(), HkscError> {
+        self.encoding = options
+            .encoding(&self.header)
+            .map_err(|error| error.within("header", reader))?;
+        self.enums = reader.read_header_enumerable::<HSEnum, T>(
+            self.header.enum_count.into(),
+            &self.header,
+            "enums",
+        )?;
+        self.main_function
+            .read::<T>(reader, &self.header, options, &self.encoding)
+            .map_err(|error| error.within("main", reader))?;
+        self.read_structures::<T>(reader, options.enable_inheritance)?;
+        Ok(())
+    }
+
+    /// Reads the structure prototypes, detecting whether they use the inheritance extension
+    /// when `enable_inheritance` is `None`.
+    pub fn read_structures<T: ByteOrder>(
+        &mut self,
+        reader: &mut impl BufReaderExt,
+        enable_inheritance: Option<bool>,
+    ) -> Result<(), HkscError> {
+        if !self.header.features.contains(HSFeatures::STRUCTURES) {
+            return Ok(());
+        }
+        let check =
+            read_int::<T>(reader, &self.header).map_err(|error| error.within("structs", reader))?;
+        if check != 1 {
+            return Ok(());
+        }
+
+        if let Some(enable_inheritance) = enable_inheritance {
+            self.enable_inheritance = enable_inheritance;
+            return Self::read_structure_list::<T>(
+                reader,
+                &self.header,
+                enable_inheritance,
+                &mut self.structs,
+            );
+        }
+
+        // Reading with the wrong layout misinterprets every field after the first structure's
+        // slot count, which leads to invalid strings or types, or to a list that doesn't end
+        // exactly at the end of the file. Both layouts are tried, preferring the one that ends
+        // the file and then the one without inheritance. When neither can be read, the one that
+        // read more structures is kept.
+        let start = reader.stream_position()?;
+        let mut layouts = Vec::with_capacity(2);
+        for enable_inheritance in [false, true] {
+            reader.seek(SeekFrom::Start(start))?;
+            let mut structs = Vec::new();
+            let result = Self::read_structure_list::<T>(
+                reader,
+                &self.header,
+                enable_inheritance,
+                &mut structs,
+            );
+            let at_end = result.is_ok() && reader.fill_buf()?.is_empty();
+            layouts.push((
+                enable_inheritance,
+                structs,
+                result,
+                at_end,
+                reader.stream_position()?,
+            ));
+        }
+        let index = layouts
+            .iter()
+            .position(|(_, _, _, at_end, _)| *at_end)
+            .or_else(|| {
+                layouts
+                    .iter()
+                    .position(|(_, _, result, _, _)| result.is_ok())
+            })
+            .unwrap_or_else(|| usize::from(layouts[1].1.len() > layouts[0].1.len()));
+        let (enable_inheritance, structs, result, _, end) = layouts.swap_remove(index);
+        self.structs = structs;
+        self.enable_inheritance = enable_inheritance;
+        self.inheritance_detected = true;
+        reader.seek(SeekFrom::Start(end))?;
+        result
+    }
+
+    /// Reads structure prototypes into `structs` until the empty name terminating the list.
+    fn read_structure_list<T: ByteOrder>(
+        reader: &mut impl BufReaderExt,
+        header: &HSHeader,
         enable_inheritance: bool,
+        structs: &mut Vec<HSStructPrototype>,
     ) -> Result<(), HkscError> {
-        self.header.read(reader)?;
+        loop {
+            let mut read_structure = || -> Result<Option<HSStructPrototype>, HkscError> {
+                let name = read_string::<T>(reader, header)?;
+                if name.is_empty() {
+                    return Ok(None);
+                }
+                let mut structure = HSStructPrototype::default();
+                structure.read::<T>(reader, header, enable_inheritance)?;
+                structure.name = name;
+                Ok(Some(structure))
+            };
+            match read_structure() {
+                Ok(Some(structure)) => structs.push(structure),
+                Ok(None) => return Ok(()),
+                Err(error) => {
+                    return Err(error.within(format!("structs[{}]", structs.len()), reader));
+                }
+            }
+        }
+    }
+
+    /// Serializes the file back into bytecode, with the structure layout of `enable_inheritance`.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, HkscError> {
+        self.to_bytes_with(self.enable_inheritance)
+    }
+
+    /// Serializes the file back into bytecode, overriding whether structures use the inheritance
+    /// extension, which changes their layout.
+    pub fn to_bytes_with(&self, enable_inheritance: bool) -> Result<Vec<u8>, HkscError> {
+        let mut cursor = Cursor::new(Vec::new());
+        self.write_with(&mut cursor, enable_inheritance)?;
+        Ok(cursor.into_inner())
+    }
+
+    /// Writes the whole file, starting at the current position of `writer`, with the structure
+    /// layout of `enable_inheritance`.
+    pub fn write<W: Write + Seek>(&self, writer: &mut W) -> Result<(), HkscError> {
+        self.write_with(writer, self.enable_inheritance)
+    }
+
+    /// Writes the whole file like `write`, overriding whether structures use the inheritance
+    /// extension.
+    pub fn write_with<W: Write + Seek>(
+        &self,
+        writer: &mut W,
+        enable_inheritance: bool,
+    ) -> Result<(), HkscError> {
+        let writer = &mut BufWriter::new(writer);
+        self.header.write(writer)?;
         if self.header.is_little_endian {
-            self.enums = reader.read_enumerable::<HSEnum, LE>(self.header.enum_count.into())?;
-            self.main_function.read::<LE>(reader, &self.header)?;
-            self.read_structures::<LE>(reader, enable_inheritance)?;
+            writer.write_header_enumerable::<HSEnum, LE>(&self.enums, &self.header)?;
+            self.main_function.write_with_encoding::<LE>(
+                writer,
+                &self.header,
+                &self.profile,
+                &self.encoding,
+            )?;
+            self.write_structures::<LE>(writer, enable_inheritance)?;
         } else {
-            self.enums = reader.read_enumerable::<HSEnum, BE>(self.header.enum_count.into())?;
-            self.main_function.read::<BE>(reader, &self.header)?;
-            self.read_structures::<BE>(reader, enable_inheritance)?;
+            writer.write_header_enumerable::<HSEnum, BE>(&self.enums, &self.header)?;
+            self.main_function.write_with_encoding::<BE>(
+                writer,
+                &self.header,
+                &self.profile,
+                &self.encoding,
+            )?;
+            self.write_structures::<BE>(writer, enable_inheritance)?;
         }
+        writer.flush()?;
         Ok(())
     }
 
-    pub fn read_structures<T: ByteOrder>(
-        &mut self,
-        reader: &mut BufReader<File>,
+    pub fn write_structures<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
         enable_inheritance: bool,
     ) -> Result<(), HkscError> {
         if self.header.features.contains(HSFeatures::STRUCTURES) {
-            let check = reader.read_u32::<T>()?;
-            if check != 1 {
+            // Anything but 1 tells the reader that no structures follow.
+            if self.structs.is_empty() {
+                write_int::<T>(writer, &self.header, 0)?;
                 return Ok(());
             }
+            write_int::<T>(writer, &self.header, 1)?;
 
-            loop {
-                let name = read_string::<T>(reader, &self.header)?;
-                if name.is_empty() {
-                    break;
-                }
-                let mut structure = HSStructPrototype::default();
-                structure.read::<T>(reader, &self.header, enable_inheritance)?;
-                structure.name = name;
-                self.structs.push(structure);
+            for structure in &self.structs {
+                write_string::<T>(writer, &self.header, &structure.name)?;
+                structure.write::<T>(writer, &self.header, enable_inheritance)?;
             }
+            // An empty name terminates the list.
+            write_string::<T>(writer, &self.header, "")?;
         }
         Ok(())
     }
@@ -77,6 +372,23 @@ impl HavokScriptFile {
 
 impl Display for HavokScriptFile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_file(f, None)
+    }
+}
+
+impl HavokScriptFile {
+    /// Returns a view that displays the file with the original source lines above the
+    /// instructions they compiled to.
+    #[must_use]
+    pub fn display_with_sources<'a>(&'a self, sources: &'a HSSources) -> impl Display + 'a {
+        std::fmt::from_fn(move |f| self.fmt_file(f, Some(sources)))
+    }
+
+    fn fmt_file(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        sources: Option<&HSSources>,
+    ) -> std::fmt::Result {
         writeln!(f, "{} \n{}", "[Header]".green(), self.header)?;
 
         writeln!(f, "{}", "[Enums]".green())?;
@@ -84,13 +396,37 @@ impl Display for HavokScriptFile {
             writeln!(f, "{item}")?;
         }
         writeln!(f)?;
-        writeln!(f, "{}", self.main_function)?;
+        let context = HSDisplayContext {
+            sources,
+            structs: &self.structs,
+            inheritance: Some(&self.inheritance),
+        };
+        self.main_function.fmt_function(f, true, context)?;
+        writeln!(f)?;
 
         if !self.structs.is_empty() {
-            for struc in &self.structs {
+            writeln!(
+                f,
+                "{} {}{}",
+                "[Structure Inheritance:".green(),
+                match (self.enable_inheritance, self.inheritance_detected) {
+                    (true, true) => "enabled (detected)",
+                    (true, false) => "enabled",
+                    (false, true) => "disabled (detected)",
+                    (false, false) => "disabled",
+                }
+                .bright_cyan(),
+                "]".green()
+            )?;
+            writeln!(f)?;
+            for (index, struc) in self.structs.iter().enumerate() {
                 writeln!(f, "{struc}")?;
-                for member in &struc.slots {
-                    writeln!(f, "{} {}", "-".yellow(), member)?;
+                for member in self.flattened_slots(index) {
+                    write!(f, "{} {}", "-".yellow(), member.slot)?;
+                    if member.is_inherited(struc) {
+                        write!(f, " {}", format!("(from {})", member.origin.name).green())?;
+                    }
+                    writeln!(f)?;
                 }
                 writeln!(f)?;
             }
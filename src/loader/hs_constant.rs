@@ -2,16 +2,18 @@ use super::{
     hs_header::HSHeader,
     hs_opcodes::HSType,
     hs_reader::{read_number, read_string},
+    hs_writer::{write_number, write_terminated_string},
 };
 use crate::{
     common::errors::HkscError,
-    common::extensions::{BufReaderExt, HeaderReadable},
+    common::extensions::{BufReaderExt, HeaderReadable, HeaderWritable, WriterExt},
 };
 
-use byteorder::{ByteOrder, ReadBytesExt};
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
 use colored::Colorize;
 use std::fmt::{Display, Formatter};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(untagged))]
 /// Possible values for a (valid) `HavokScript` constant.
 pub enum HSValue {
     /// Null pointer.
@@ -29,9 +31,11 @@ pub enum HSValue {
 }
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Represents a constant, containing a type and value.
 pub struct HSConstant {
     /// Type of constant, dictates how it is read
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: HSType,
     /// Value of the constant, mapping to a `HSValue` enum.
     pub value: Option<HSValue>,
@@ -64,6 +68,31 @@ impl HeaderReadable for HSConstant {
     }
 }
 
+impl HeaderWritable for HSConstant {
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        writer.write_u8(self.type_.into())?;
+
+        match &self.value {
+            None | Some(HSValue::Nil) => {}
+            Some(HSValue::LightUserData(value)) => match header.t_size {
+                4 => writer.write_u32::<T>(u32::try_from(*value)?)?,
+                8 => writer.write_u64::<T>(*value)?,
+                _ => return Err(HkscError::InvalidLightUserDataSize(header.t_size)),
+            },
+            Some(HSValue::Boolean(value)) => writer.write_u8((*value).into())?,
+            Some(HSValue::String(value)) => write_terminated_string::<T>(writer, header, value)?,
+            Some(HSValue::Number(value)) => write_number::<T>(writer, header, *value)?,
+            Some(HSValue::Ui64(value)) => writer.write_u64::<T>(*value)?,
+        }
+
+        Ok(())
+    }
+}
+
 impl Display for HSConstant {
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         match &self.value {
@@ -1,22 +1,45 @@
-use super::{hs_header::HSHeader, hs_reader::read_string};
+use super::{
+    hs_header::HSHeader,
+    hs_reader::{read_int, read_string},
+    hs_writer::{write_int, write_string},
+};
 use crate::{
     common::errors::HkscError,
-    common::extensions::{BufReaderExt, HeaderReadable},
+    common::extensions::{BufReaderExt, HeaderReadable, HeaderWritable, WriterExt},
 };
 
-use byteorder::{ByteOrder, ReadBytesExt};
+use byteorder::ByteOrder;
 use colored::Colorize;
 use std::fmt::Display;
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Local variable information for a function.
 pub struct HSFunctionDebugInfoLocals {
     /// Name of the local variable.
     pub local_name: String,
-    /// Start line of the local variable.
-    start: u32,
-    /// End line of the local variable.
-    end: u32,
+    /// Index of the first instruction where the local variable is active.
+    pub start: u32,
+    /// Index of the instruction after the last one where the local variable is active.
+    pub end: u32,
+}
+
+impl HSFunctionDebugInfoLocals {
+    /// Creates a local variable from its name and range.
+    #[must_use]
+    pub fn new(local_name: String, start: u32, end: u32) -> Self {
+        Self {
+            local_name,
+            start,
+            end,
+        }
+    }
+
+    /// Returns whether the local variable is active at the instruction `pc`.
+    #[must_use]
+    pub fn is_active(&self, pc: usize) -> bool {
+        u32::try_from(pc).is_ok_and(|pc| self.start <= pc && pc < self.end)
+    }
 }
 
 impl HeaderReadable for HSFunctionDebugInfoLocals {
@@ -26,13 +49,27 @@ impl HeaderReadable for HSFunctionDebugInfoLocals {
         header: &HSHeader,
     ) -> Result<(), HkscError> {
         self.local_name = read_string::<T>(reader, header)?;
-        self.start = reader.read_u32::<T>()?;
-        self.end = reader.read_u32::<T>()?;
+        self.start = read_int::<T>(reader, header)?;
+        self.end = read_int::<T>(reader, header)?;
+        Ok(())
+    }
+}
+
+impl HeaderWritable for HSFunctionDebugInfoLocals {
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        write_string::<T>(writer, header, &self.local_name)?;
+        write_int::<T>(writer, header, self.start)?;
+        write_int::<T>(writer, header, self.end)?;
         Ok(())
     }
 }
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Debug information for a function, containing data to read local variables and up values.
 pub struct HSFunctionDebugInfo {
     /// Number of lines in the function.
@@ -57,37 +94,92 @@ pub struct HSFunctionDebugInfo {
     pub up_values: Vec<String>,
 }
 
+impl HSFunctionDebugInfo {
+    /// Returns the local variable held in `register` at the instruction `pc`.
+    ///
+    /// Active locals occupy the lowest registers in the order they were declared, so the local
+    /// in `register` is the `register`-th one active at `pc`.
+    #[must_use]
+    pub fn local_at(&self, register: usize, pc: usize) -> Option<&HSFunctionDebugInfoLocals> {
+        self.locals
+            .iter()
+            .filter(|local| local.is_active(pc))
+            .nth(register)
+    }
+
+    /// Returns the name of an upvalue, if it has one.
+    #[must_use]
+    pub fn up_value_name(&self, index: usize) -> Option<&str> {
+        self.up_values
+            .get(index)
+            .map(String::as_str)
+            .filter(|name| !name.is_empty())
+    }
+}
+
 impl HeaderReadable for HSFunctionDebugInfo {
     fn read<T: ByteOrder>(
         &mut self,
         reader: &mut impl BufReaderExt,
         header: &HSHeader,
     ) -> Result<(), HkscError> {
-        self.line_count = reader.read_u32::<T>()?;
-        self.locals_count = reader.read_u32::<T>()?;
-        self.up_value_count = reader.read_u32::<T>()?;
-        self.line_begin = reader.read_u32::<T>()?;
-        self.line_end = reader.read_u32::<T>()?;
+        self.line_count = read_int::<T>(reader, header)?;
+        self.locals_count = read_int::<T>(reader, header)?;
+        self.up_value_count = read_int::<T>(reader, header)?;
+        self.line_begin = read_int::<T>(reader, header)?;
+        self.line_end = read_int::<T>(reader, header)?;
         self.path = read_string::<T>(reader, header)?;
         self.function_name = read_string::<T>(reader, header)?;
 
         self.lines = (0..self.line_count)
-            .map(|_| reader.read_u32::<T>())
+            .map(|_| read_int::<T>(reader, header))
             .collect::<Result<_, _>>()?;
 
         self.locals = reader.read_header_enumerable::<HSFunctionDebugInfoLocals, T>(
             self.locals_count.into(),
             header,
+            "locals",
         )?;
 
         self.up_values = (0..self.up_value_count)
-            .map(|_| read_string::<T>(reader, header))
+            .map(|index| {
+                read_string::<T>(reader, header)
+                    .map_err(|error| error.within(format!("up_values[{index}]"), reader))
+            })
             .collect::<Result<_, _>>()?;
 
         Ok(())
     }
 }
 
+impl HeaderWritable for HSFunctionDebugInfo {
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        write_int::<T>(writer, header, u32::try_from(self.lines.len())?)?;
+        write_int::<T>(writer, header, u32::try_from(self.locals.len())?)?;
+        write_int::<T>(writer, header, u32::try_from(self.up_values.len())?)?;
+        write_int::<T>(writer, header, self.line_begin)?;
+        write_int::<T>(writer, header, self.line_end)?;
+        write_string::<T>(writer, header, &self.path)?;
+        write_string::<T>(writer, header, &self.function_name)?;
+
+        for line in &self.lines {
+            write_int::<T>(writer, header, *line)?;
+        }
+
+        writer.write_header_enumerable::<HSFunctionDebugInfoLocals, T>(&self.locals, header)?;
+
+        for up_value in &self.up_values {
+            write_string::<T>(writer, header, up_value)?;
+        }
+
+        Ok(())
+    }
+}
+
 impl Display for HSFunctionDebugInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
@@ -0,0 +1,59 @@
+use crate::common::errors::HkscError;
+
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Problem found while reading a file with `HSReadOptions::recover`, which is reported
+/// instead of failing the read.
+pub struct HSDiagnostic {
+    /// Path of the element the problem was found in, such as `main > child[3] > constants[17]`.
+    /// Empty for notes about the file as a whole.
+    pub path: Vec<String>,
+    /// Offset the reader had reached, if known.
+    pub offset: Option<u64>,
+    /// Description of the problem.
+    pub message: String,
+}
+
+impl HSDiagnostic {
+    /// Creates a diagnostic that isn't tied to an element of the file.
+    pub fn note(message: impl Into<String>) -> Self {
+        Self {
+            path: Vec::new(),
+            offset: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<HkscError> for HSDiagnostic {
+    fn from(error: HkscError) -> Self {
+        match error {
+            HkscError::Context {
+                offset,
+                path,
+                source,
+            } => Self {
+                path,
+                offset: Some(offset),
+                message: source.to_string(),
+            },
+            error => Self::note(error.to_string()),
+        }
+    }
+}
+
+impl Display for HSDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        match (self.path.is_empty(), self.offset) {
+            (false, Some(offset)) => {
+                write!(f, " (in {}, at offset {offset:#x})", self.path.join(" > "))
+            }
+            (false, None) => write!(f, " (in {})", self.path.join(" > ")),
+            (true, Some(offset)) => write!(f, " (at offset {offset:#x})"),
+            (true, None) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::common::errors::HkscError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// A bit field of an instruction word.
+pub struct HSField {
+    /// Position of the lowest bit of the field.
+    pub shift: u32,
+    /// Number of bits in the field, at most 32.
+    pub width: u32,
+}
+
+impl HSField {
+    /// Creates a field of `width` bits, starting at bit `shift`.
+    #[must_use]
+    pub const fn new(shift: u32, width: u32) -> Self {
+        Self { shift, width }
+    }
+
+    /// Returns the field with one more bit, which 'B' and 'C' arguments use to tell constants
+    /// from registers and for offsets.
+    #[must_use]
+    pub const fn extended(self) -> Self {
+        Self::new(self.shift, self.width + 1)
+    }
+
+    /// Returns the bits of the field at its position in the instruction word.
+    #[must_use]
+    pub const fn mask(self) -> u64 {
+        ((1 << self.width) - 1) << self.shift
+    }
+
+    /// Returns the value of the field in `word`.
+    #[must_use]
+    pub const fn get(self, word: u64) -> i32 {
+        // Fields are at most 32 bits wide, the widest of which are read as their bit pattern.
+        #[allow(clippy::cast_possible_truncation)]
+        let value = ((word & self.mask()) >> self.shift) as u32;
+        value.cast_signed()
+    }
+
+    /// Returns `word` with the field set to `value`, truncated to the width of the field.
+    #[must_use]
+    pub const fn set(self, word: u64, value: i32) -> u64 {
+        (word & !self.mask()) | (((value.cast_unsigned() as u64) << self.shift) & self.mask())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Layout of the fields of an instruction word.
+///
+/// 'B' and 'C' arguments holding either a register or a constant, or an offset, use one more bit
+/// than their field, directly above it.
+pub struct HSInstructionEncoding {
+    /// Size of an instruction word in bytes. Only 4 byte words can be read and written.
+    pub size: u8,
+    /// Field holding the opcode number.
+    pub opcode: HSField,
+    /// Field of the 'A' argument.
+    pub a: HSField,
+    /// Field of the 'B' argument of ABC format instructions.
+    pub b: HSField,
+    /// Field of the 'C' argument of ABC format instructions.
+    pub c: HSField,
+    /// Field of the 'B' argument of other formats, spanning both 'B' and 'C'.
+    pub bx: HSField,
+}
+
+impl Default for HSInstructionEncoding {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+impl HSInstructionEncoding {
+    /// Encoding of 4 byte instructions, as produced by every known `HavokScript` compiler.
+    pub const STANDARD: Self = Self {
+        size: 4,
+        opcode: HSField::new(25, 7),
+        a: HSField::new(0, 8),
+        b: HSField::new(17, 8),
+        c: HSField::new(8, 8),
+        bx: HSField::new(8, 17),
+    };
+
+    /// Returns the encoding of instructions of `size` bytes, as declared by a file's header.
+    pub fn for_size(size: u8) -> Result<Self, HkscError> {
+        match size {
+            4 => Ok(Self::STANDARD),
+            _ => Err(HkscError::UnsupportedInstructionSize(size)),
+        }
+    }
+
+    /// Returns the value subtracted from the 'B' argument of `AsBx` format instructions, so that
+    /// it can hold negative offsets.
+    #[must_use]
+    pub const fn sbx_bias(&self) -> i32 {
+        ((1_u32 << (self.bx.width - 1)) - 1).cast_signed()
+    }
+}
@@ -1,32 +1,64 @@
+use super::{hs_header::HSHeader, hs_reader::read_int, hs_writer::write_int};
 use crate::{
     common::errors::HkscError,
-    common::extensions::{BufReaderExt, Readable},
+    common::extensions::{BufReaderExt, HeaderReadable, HeaderWritable, WriterExt},
 };
 
-use byteorder::{ByteOrder, ReadBytesExt};
+use byteorder::ByteOrder;
 use colored::Colorize;
 use std::fmt::Display;
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Represents an enum in a `HavokScript` file.
 pub struct HSEnum {
     /// Value of the enum (index).
     pub value: u32,
-    /// Length of the name of the enum.
-    length: u32,
+    /// Length of the name field, which is padded with null bytes when longer than the name.
+    pub length: u32,
     /// Name of the enum.
     pub name: String,
 }
 
-impl Readable for HSEnum {
-    fn read<T: ByteOrder>(&mut self, reader: &mut impl BufReaderExt) -> Result<(), HkscError> {
-        self.value = reader.read_u32::<T>()?;
-        self.length = reader.read_u32::<T>()?;
+impl HSEnum {
+    /// Creates an enum from its name and value.
+    #[must_use]
+    pub fn new(name: String, value: u32) -> Self {
+        Self {
+            value,
+            length: u32::try_from(name.len()).unwrap_or(u32::MAX),
+            name,
+        }
+    }
+}
+
+impl HeaderReadable for HSEnum {
+    fn read<T: ByteOrder>(
+        &mut self,
+        reader: &mut impl BufReaderExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        self.value = read_int::<T>(reader, header)?;
+        self.length = read_int::<T>(reader, header)?;
         self.name = reader.read_fixed_string::<T>(self.length as usize)?;
         Ok(())
     }
 }
 
+impl HeaderWritable for HSEnum {
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        // Keep the original field length so unmodified enums round-trip exactly.
+        let length = (self.length as usize).max(self.name.len());
+        write_int::<T>(writer, header, self.value)?;
+        write_int::<T>(writer, header, u32::try_from(length)?)?;
+        writer.write_fixed_string::<T>(&self.name, length)
+    }
+}
+
 impl Display for HSEnum {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -1,19 +1,39 @@
 use super::{
-    hs_constant::HSConstant, hs_debug::HSFunctionDebugInfo, hs_header::HSHeader,
-    hs_instruction::HSInstruction, hs_opcodes::HSOpArgMode,
+    hs_constant::HSConstant,
+    hs_debug::HSFunctionDebugInfo,
+    hs_encoding::HSInstructionEncoding,
+    hs_header::HSHeader,
+    hs_inheritance::HSInheritance,
+    hs_instruction::{HSInstruction, HSInstructionArg},
+    hs_opcodes::{HSOpArgMode, HSOpCode, HSType},
+    hs_options::HSReadOptions,
+    hs_profile::HSOpcodeProfile,
+    hs_reader::{read_int, read_size},
+    hs_source::HSSources,
+    hs_structure::{HSStructPrototype, HSStructSlot, HSTypeCode},
+    hs_writer::{write_int, write_size},
 };
 use crate::{
+    cfg::HSControlFlowGraph,
     common::errors::HkscError,
-    common::extensions::{BufReaderExt, HeaderReadable},
+    common::extensions::{BufReaderExt, HeaderReadable, HeaderWritable, WriterExt},
 };
 
 use bitflags::bitflags;
-use byteorder::{ByteOrder, ReadBytesExt};
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
 use colored::Colorize;
-use std::{fmt::Display, io::SeekFrom};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    io::SeekFrom,
+};
+
+/// Number of array items each `SetList` batch stores.
+pub const FIELDS_PER_FLUSH: usize = 50;
 
 bitflags! {
     #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     /// Flag that sets if a function supports variadic arguments.
     pub struct HSVarArg : u8 {
         /// Function has no variadic arguments.
@@ -36,7 +56,19 @@ impl Display for HSVarArg {
     }
 }
 
+#[derive(Clone, Copy, Default)]
+/// Information from outside of a function that its disassembly is annotated with.
+pub struct HSDisplayContext<'a> {
+    /// Original Lua sources, whose lines are shown above the instructions they compiled to.
+    pub sources: Option<&'a HSSources>,
+    /// Structure prototypes of the file, which structure ids are resolved to.
+    pub structs: &'a [HSStructPrototype],
+    /// Inheritance of `structs`, which is resolved from their names when `None`.
+    pub inheritance: Option<&'a HSInheritance>,
+}
+
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Function definition in a `HavokScript` file.
 pub struct HSFunction {
     /// Number of up values in the function.
@@ -64,21 +96,35 @@ pub struct HSFunction {
     pub function_count: u32,
     /// Child functions in the function.
     pub child_functions: Vec<HSFunction>,
-    /// Offset of the function in the file.
-    pub function_offset: u64,
+    /// Offset of the start of the function in the file.
+    pub start_offset: u64,
+    /// Offset of the end of the function in the file, after all of its children.
+    pub end_offset: u64,
+    /// Number of functions enclosing this one, 0 for the main function.
+    pub depth: usize,
+    /// Path of child indices leading to the function, such as `0/3/1` for the second child of
+    /// the fourth child of the main function. Set by `assign_ids`.
+    pub id: String,
 }
 
-impl HeaderReadable for HSFunction {
-    fn read<T: ByteOrder>(
+impl HSFunction {
+    /// Reads a function and all of its children, decoding instructions with `encoding`.
+    ///
+    /// Instructions and children are added as they are read, so a function that fails to read
+    /// keeps everything before the failure.
+    pub fn read<T: ByteOrder>(
         &mut self,
         reader: &mut impl BufReaderExt,
         header: &HSHeader,
+        options: &HSReadOptions,
+        encoding: &HSInstructionEncoding,
     ) -> Result<(), HkscError> {
-        self.up_value_count = reader.read_u32::<T>()?;
-        self.param_count = reader.read_u32::<T>()?;
+        self.start_offset = reader.stream_position()?;
+        self.up_value_count = read_int::<T>(reader, header)?;
+        self.param_count = read_int::<T>(reader, header)?;
         self.var_arg = HSVarArg::from_bits_truncate(reader.read_u8()?);
-        self.slot_count = reader.read_u32::<T>()?;
-        self.instruction_count = reader.read_u64::<T>()?;
+        self.slot_count = read_int::<T>(reader, header)?;
+        self.instruction_count = read_size::<T>(reader, header)?;
 
         // This aligns the reader to the next 4 byte boundary.
         let current_pos = reader.stream_position()?;
@@ -86,41 +132,435 @@ impl HeaderReadable for HSFunction {
         let aligned_pos = (current_pos + (instruction_size - 1)) & !(instruction_size - 1);
         reader.seek(SeekFrom::Start(aligned_pos))?;
 
-        self.instructions = reader.read_enumerable::<HSInstruction, T>(self.instruction_count)?;
-        self.constant_count = reader.read_u32::<T>()?;
-        self.constants =
-            reader.read_header_enumerable::<HSConstant, T>(self.constant_count.into(), header)?;
-        self.has_debug_info = reader.read_u32::<T>()? != 0;
+        for index in 0..self.instruction_count {
+            let mut instruction = HSInstruction::default();
+            if options.lenient {
+                instruction.read_lenient::<T>(reader, &options.profile, encoding)
+            } else {
+                instruction.read_with_encoding::<T>(reader, &options.profile, encoding)
+            }
+            .map_err(|error| error.within(format!("instructions[{index}]"), reader))?;
+            self.instructions.push(instruction);
+        }
+        self.constant_count = read_int::<T>(reader, header)?;
+        self.constants = reader.read_header_enumerable::<HSConstant, T>(
+            self.constant_count.into(),
+            header,
+            "constants",
+        )?;
+        self.has_debug_info = read_int::<T>(reader, header)? != 0;
         if self.has_debug_info {
-            self.debug_info.read::<T>(reader, header)?;
+            self.debug_info
+                .read::<T>(reader, header)
+                .map_err(|error| error.within("debug", reader))?;
+        }
+        self.function_count = read_int::<T>(reader, header)?;
+        for index in 0..self.function_count {
+            let mut function = HSFunction::default();
+            let result = function.read::<T>(reader, header, options, encoding);
+            self.child_functions.push(function);
+            result.map_err(|error| error.within(format!("child[{index}]"), reader))?;
+        }
+        self.end_offset = reader.stream_position()?;
+        Ok(())
+    }
+
+    /// Sets the `id` and `depth` of this function and all of its children, `id` being the
+    /// path of this function.
+    pub fn assign_ids(&mut self, id: &str, depth: usize) {
+        self.id = id.to_string();
+        self.depth = depth;
+        for (index, child) in self.child_functions.iter_mut().enumerate() {
+            child.assign_ids(&format!("{id}/{index}"), depth + 1);
+        }
+    }
+
+    /// Returns the function with the given `id` in this function's tree.
+    #[must_use]
+    pub fn find(&self, id: &str) -> Option<&HSFunction> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.child_functions.iter().find_map(|child| child.find(id))
+    }
+
+    /// Returns the number of functions in this function's tree, including itself.
+    #[must_use]
+    pub fn count_functions(&self) -> usize {
+        1 + self
+            .child_functions
+            .iter()
+            .map(HSFunction::count_functions)
+            .sum::<usize>()
+    }
+
+    /// Returns the child function created by the `Closure` at `pc`, with its index.
+    #[must_use]
+    pub fn closure_child(&self, pc: usize) -> Option<(usize, &HSFunction)> {
+        let instruction = self.instructions.get(pc)?;
+        if instruction.mode != HSOpCode::Closure {
+            return None;
+        }
+        let index = usize::try_from(instruction.args.get(1)?.value).ok()?;
+        Some((index, self.child_functions.get(index)?))
+    }
+
+    /// Returns the payload of the `Data` instruction following the instruction at `pc`, if that
+    /// instruction is one that `Data` extends, which are `SetList` and `NewStruct`.
+    #[must_use]
+    pub fn data(&self, pc: usize) -> Option<i32> {
+        let instruction = self.instructions.get(pc)?;
+        if !matches!(instruction.mode, HSOpCode::SetList | HSOpCode::NewStruct) {
+            return None;
+        }
+        let data = self.instructions.get(pc + 1)?;
+        if data.mode != HSOpCode::Data {
+            return None;
+        }
+        Some(data.args.get(1)?.value)
+    }
+
+    /// Returns whether the instruction at `pc` is a `Data` extending the instruction before it.
+    #[must_use]
+    pub fn extends_previous(&self, pc: usize) -> bool {
+        pc > 0 && self.data(pc - 1).is_some()
+    }
+
+    /// Returns the 1-based batch of a `SetList` at `pc`, taken from the following `Data` when
+    /// its C argument is 0, as batches that don't fit in C are stored there.
+    #[must_use]
+    pub fn set_list_batch(&self, pc: usize) -> Option<usize> {
+        let instruction = self.instructions.get(pc)?;
+        if instruction.mode != HSOpCode::SetList {
+            return None;
+        }
+        match instruction.args.get(2).map_or(0, |arg| arg.value) {
+            0 => usize::try_from(self.data(pc)?).ok(),
+            batch => usize::try_from(batch).ok(),
+        }
+    }
+
+    /// Returns the id of the structure prototype a `NewStruct` at `pc` creates, which is stored
+    /// in the following `Data`.
+    #[must_use]
+    pub fn new_struct_id(&self, pc: usize) -> Option<u64> {
+        if self.instructions.get(pc)?.mode != HSOpCode::NewStruct {
+            return None;
+        }
+        u64::try_from(self.data(pc)?).ok()
+    }
+
+    /// Returns whether the instruction is a `CheckType` variant, whose B argument is a type code.
+    #[must_use]
+    pub fn checks_type(instruction: &HSInstruction) -> bool {
+        matches!(
+            instruction.mode,
+            HSOpCode::CheckType | HSOpCode::CheckTypes | HSOpCode::CheckTypeD
+        )
+    }
+
+    /// Returns the indices of the object register and slot arguments of a slot instruction.
+    #[must_use]
+    pub fn slot_operands(instruction: &HSInstruction) -> Option<(usize, usize)> {
+        match instruction.mode {
+            HSOpCode::SetSlotN
+            | HSOpCode::SetSlotI
+            | HSOpCode::SetSlot
+            | HSOpCode::SetSlotS
+            | HSOpCode::SetSlotMt => Some((0, 1)),
+            HSOpCode::GetSlot
+            | HSOpCode::GetSlotMt
+            | HSOpCode::GetSlotD
+            | HSOpCode::SelfSlot
+            | HSOpCode::SelfSlotMt => Some((1, 2)),
+            _ => None,
+        }
+    }
+
+    /// Returns the structure that the object of each slot instruction is known to be, `None`
+    /// for other instructions.
+    ///
+    /// Structures are inferred along the control-flow graph: `NewStruct` and `CheckType` give a
+    /// register its structure, `Move` and `GetSlot` of a struct-typed slot pass one on, and any
+    /// other write to a register forgets it. Where control flow merges, a register keeps its
+    /// structure only if every path into the merge agrees on it. Slots are looked up in the
+    /// flattened slot set of each structure, so inherited slots are found as well.
+    #[must_use]
+    pub fn slot_structs<'a>(
+        &self,
+        structs: &'a [HSStructPrototype],
+        inheritance: &HSInheritance,
+    ) -> Vec<Option<&'a HSStructPrototype>> {
+        let graph = HSControlFlowGraph::new(self);
+        // Structures known at the start of each block, `None` until control is found to reach it.
+        let mut entries = vec![None::<BTreeMap<i32, &HSStructPrototype>>; graph.blocks.len()];
+        let mut pending = BTreeSet::new();
+        if let Some(entry) = entries.first_mut() {
+            *entry = Some(BTreeMap::new());
+            pending.insert(0);
+        }
+        while let Some(block) = pending.pop_first() {
+            let mut registers = entries[block].clone().unwrap_or_default();
+            for pc in graph.blocks[block].start..graph.blocks[block].end {
+                self.infer_structs(pc, structs, inheritance, &mut registers);
+            }
+            for edge in graph.successors(block) {
+                let changed = match &mut entries[edge.to] {
+                    Some(entry) => {
+                        let len = entry.len();
+                        entry.retain(|register, structure| {
+                            registers
+                                .get(register)
+                                .is_some_and(|known| std::ptr::eq(*known, *structure))
+                        });
+                        entry.len() != len
+                    }
+                    entry @ None => {
+                        *entry = Some(registers.clone());
+                        true
+                    }
+                };
+                if changed {
+                    pending.insert(edge.to);
+                }
+            }
+        }
+
+        let mut objects = Vec::with_capacity(self.instructions.len());
+        for (block, entry) in graph.blocks.iter().zip(entries) {
+            let mut registers = entry.unwrap_or_default();
+            for pc in block.start..block.end {
+                objects.push(self.infer_structs(pc, structs, inheritance, &mut registers));
+            }
+        }
+        objects
+    }
+
+    /// Updates `registers` with the effect of the instruction at `pc`, returning the structure
+    /// that the object of a slot instruction is known to be.
+    fn infer_structs<'a>(
+        &self,
+        pc: usize,
+        structs: &'a [HSStructPrototype],
+        inheritance: &HSInheritance,
+        registers: &mut BTreeMap<i32, &'a HSStructPrototype>,
+    ) -> Option<&'a HSStructPrototype> {
+        let inst = &self.instructions[pc];
+        let find = |id: Option<u64>| structs.iter().find(|structure| Some(structure.id) == id);
+        let arg = |index: usize| inst.args.get(index).map_or(0, |arg| arg.value);
+        let object =
+            Self::slot_operands(inst).and_then(|(object, _)| registers.get(&arg(object)).copied());
+        // Structure held by the slot that a `GetSlot` or `SelfSlot` reads.
+        let slot = || {
+            object
+                .and_then(|structure| Self::slot_of(structs, inheritance, structure, arg(2)))
+                .filter(|slot| slot.type_ == HSType::TSTRUCT)
+                .and_then(|slot| find(Some(slot.struct_id)))
+        };
+        let a = arg(0);
+        match inst.mode {
+            HSOpCode::NewStruct => {
+                Self::set_struct(registers, a, find(self.new_struct_id(pc)));
+            }
+            HSOpCode::CheckType | HSOpCode::CheckTypeD => {
+                if let HSTypeCode::Struct(structure) = HSTypeCode::resolve(arg(1), structs) {
+                    Self::set_struct(registers, a, Some(structure));
+                }
+            }
+            HSOpCode::GetSlot | HSOpCode::GetSlotMt | HSOpCode::GetSlotD => {
+                Self::set_struct(registers, a, slot());
+            }
+            HSOpCode::SelfSlot | HSOpCode::SelfSlotMt => {
+                Self::set_struct(registers, a + 1, object);
+                Self::set_struct(registers, a, slot());
+            }
+            HSOpCode::Move => {
+                let source = registers.get(&arg(1)).copied();
+                Self::set_struct(registers, a, source);
+            }
+            HSOpCode::Call
+            | HSOpCode::CallI
+            | HSOpCode::CallC
+            | HSOpCode::CallM
+            | HSOpCode::TailCall
+            | HSOpCode::TailCallI
+            | HSOpCode::TailCallC
+            | HSOpCode::TailCallM
+            | HSOpCode::Vararg
+            | HSOpCode::LoadNil
+            | HSOpCode::TForLoop => registers.retain(|register, _| *register < a),
+            mode if Self::writes_a(mode)
+                && inst
+                    .args
+                    .first()
+                    .is_some_and(|arg| arg.mode == HSOpArgMode::REG) =>
+            {
+                Self::set_struct(registers, a, None);
+            }
+            _ => {}
+        }
+        object
+    }
+
+    /// Returns the slot at `position` in the flattened slot set of `structure`, one of `structs`.
+    fn slot_of<'a>(
+        structs: &'a [HSStructPrototype],
+        inheritance: &HSInheritance,
+        structure: &HSStructPrototype,
+        position: i32,
+    ) -> Option<&'a HSStructSlot> {
+        let index = structs
+            .iter()
+            .position(|candidate| std::ptr::eq(candidate, structure))?;
+        inheritance
+            .slot_at(structs, index, position)
+            .map(|member| member.slot)
+    }
+
+    /// Records that `register` holds `structure`, or forgets what it holds when `None`.
+    fn set_struct<'a>(
+        registers: &mut BTreeMap<i32, &'a HSStructPrototype>,
+        register: i32,
+        structure: Option<&'a HSStructPrototype>,
+    ) {
+        match structure {
+            Some(structure) => registers.insert(register, structure),
+            None => registers.remove(&register),
         };
-        self.function_count = reader.read_u32::<T>()?;
-        self.child_functions =
-            reader.read_header_enumerable::<HSFunction, T>(self.function_count.into(), header)?;
-        self.function_offset = reader.stream_position()?;
+    }
+
+    /// Returns whether an instruction with a register A argument writes to it, instead of
+    /// storing it somewhere else or only testing it.
+    fn writes_a(mode: HSOpCode) -> bool {
+        !matches!(
+            mode,
+            HSOpCode::SetField
+                | HSOpCode::SetFieldR1
+                | HSOpCode::SetTableS
+                | HSOpCode::SetTableSBk
+                | HSOpCode::SetTableN
+                | HSOpCode::SetTableNBk
+                | HSOpCode::SetTable
+                | HSOpCode::SetTableBk
+                | HSOpCode::SetGlobal
+                | HSOpCode::SetUpval
+                | HSOpCode::SetUpvalR1
+                | HSOpCode::SetSlotN
+                | HSOpCode::SetSlotI
+                | HSOpCode::SetSlot
+                | HSOpCode::SetSlotS
+                | HSOpCode::SetSlotMt
+                | HSOpCode::SetList
+                | HSOpCode::Return
+                | HSOpCode::Close
+                | HSOpCode::CheckType
+                | HSOpCode::CheckTypes
+                | HSOpCode::CheckTypeD
+                | HSOpCode::Test
+                | HSOpCode::TestR1
+        )
+    }
+
+    /// Returns a view that displays this function without its children.
+    #[must_use]
+    pub fn display_single<'a>(&'a self, context: HSDisplayContext<'a>) -> impl Display + 'a {
+        std::fmt::from_fn(move |f| self.fmt_function(f, false, context))
+    }
+}
+
+impl HeaderWritable for HSFunction {
+    /// Writes the function and all of its children, encoding instructions with the built-in
+    /// opcode profile and the encoding for the header's instruction size.
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        self.write_with_encoding::<T>(
+            writer,
+            header,
+            HSOpcodeProfile::builtin(),
+            &HSInstructionEncoding::for_size(header.instruction_size)?,
+        )
+    }
+}
+
+impl HSFunction {
+    /// Writes the function and all of its children, encoding instructions with `encoding` and
+    /// numbering their opcodes with `profile`.
+    pub fn write_with_encoding<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+        profile: &HSOpcodeProfile,
+        encoding: &HSInstructionEncoding,
+    ) -> Result<(), HkscError> {
+        write_int::<T>(writer, header, self.up_value_count)?;
+        write_int::<T>(writer, header, self.param_count)?;
+        writer.write_u8(self.var_arg.bits())?;
+        write_int::<T>(writer, header, self.slot_count)?;
+        write_size::<T>(writer, header, u64::try_from(self.instructions.len())?)?;
+
+        // Pads the writer to the next 4 byte boundary, mirroring the alignment done when reading.
+        let current_pos = writer.stream_position()?;
+        let instruction_size = u64::from(header.instruction_size);
+        let aligned_pos = (current_pos + (instruction_size - 1)) & !(instruction_size - 1);
+        writer.write_all(&vec![0; usize::try_from(aligned_pos - current_pos)?])?;
+
+        for instruction in &self.instructions {
+            instruction.write_with_encoding::<T>(writer, profile, encoding)?;
+        }
+        write_int::<T>(writer, header, u32::try_from(self.constants.len())?)?;
+        writer.write_header_enumerable::<HSConstant, T>(&self.constants, header)?;
+        write_int::<T>(writer, header, self.has_debug_info.into())?;
+        if self.has_debug_info {
+            self.debug_info.write::<T>(writer, header)?;
+        }
+        write_int::<T>(writer, header, u32::try_from(self.child_functions.len())?)?;
+        for child in &self.child_functions {
+            child.write_with_encoding::<T>(writer, header, profile, encoding)?;
+        }
         Ok(())
     }
 }
 
-impl Display for HSFunction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl HSFunction {
+    /// Returns the name of the function followed by its id, or only its id without a name.
+    fn label(&self) -> String {
         if self.has_debug_info && !self.debug_info.function_name.is_empty() {
-            writeln!(
-                f,
-                "{} {}{}",
-                "[Function:".green(),
-                self.debug_info.function_name.bright_cyan(),
-                "]".green()
-            )?;
+            format!("{} ({})", self.debug_info.function_name, self.id)
         } else {
-            writeln!(
-                f,
-                "{} {}{}",
-                "[Function:".green(),
-                self.function_offset.to_string().bright_cyan(),
-                "]".green()
-            )?;
+            self.id.clone()
         }
+    }
+
+    /// Writes the function, followed by its children when `children` is set.
+    /// Instructions are annotated with the sources and structures of `context`.
+    pub(crate) fn fmt_function(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        children: bool,
+        context: HSDisplayContext<'_>,
+    ) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} {}{}",
+            "[Function:".green(),
+            self.label().bright_cyan(),
+            "]".green()
+        )?;
+        writeln!(
+            f,
+            "{} {}",
+            "- Offsets:".yellow(),
+            format!("{:#x}..{:#x}", self.start_offset, self.end_offset).bright_cyan()
+        )?;
+        writeln!(
+            f,
+            "{} {}",
+            "- Depth:".yellow(),
+            self.depth.to_string().bright_cyan()
+        )?;
         writeln!(
             f,
             "{} {}",
@@ -147,49 +587,309 @@ impl Display for HSFunction {
         )?;
 
         writeln!(f, "{}", "Instructions:".bright_blue())?;
-        for inst in &self.instructions {
+        self.fmt_instructions(f, context)?;
+
+        if self.has_debug_info {
+            writeln!(f, "{}", "Debug Info:".bright_blue())?;
+            write!(f, "{}", self.debug_info)?;
+        }
+
+        writeln!(f)?;
+        if children {
+            for func in &self.child_functions {
+                func.fmt_function(f, true, context)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the instructions with their indices, labelling branch targets and marking
+    /// instructions that the previous one may skip. Closures show the function they create,
+    /// and the `Move`/`GetUpval` instructions following them are shown as its upvalue captures.
+    fn fmt_instructions(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        context: HSDisplayContext<'_>,
+    ) -> std::fmt::Result {
+        // Branch targets get a label so jumps can be followed without computing offsets.
+        let targets = self
+            .instructions
+            .iter()
+            .enumerate()
+            .filter_map(|(pc, inst)| inst.jump_target(pc))
+            .filter(|target| *target < self.instructions.len())
+            .collect::<BTreeSet<_>>();
+        let width = self.instructions.len().saturating_sub(1).to_string().len();
+        let lines = if self.has_debug_info {
+            self.debug_info.lines.as_slice()
+        } else {
+            &[]
+        };
+        let line_width = lines.iter().max().map_or(0, |line| line.to_string().len());
+        let resolved;
+        let inheritance = if let Some(inheritance) = context.inheritance {
+            inheritance
+        } else {
+            resolved = HSInheritance::resolve(context.structs);
+            &resolved
+        };
+        let slot_structs = self.slot_structs(context.structs, inheritance);
+        // Child function of the last closure, and the index of its next upvalue capture.
+        let mut capturing: Option<(&HSFunction, usize)> = None;
+        for (pc, inst) in self.instructions.iter().enumerate() {
+            let line = lines.get(pc).copied();
+            if let Some(sources) = context.sources
+                && let Some(line) = line
+                && (pc == 0 || lines.get(pc - 1) != Some(&line))
+                && let Some(text) = sources.line(&self.debug_info.path, line)
+            {
+                writeln!(
+                    f,
+                    "{}",
+                    format!("-- {line}: {}", text.trim()).bright_black()
+                )?;
+            }
+            if targets.contains(&pc) {
+                writeln!(f, "{}", format!("L{pc}:").green())?;
+            }
             write!(
                 f,
-                "{} {}{} ",
+                "{} {} ",
                 "-".yellow(),
-                inst.mode.to_string().yellow(),
-                ":".yellow()
+                format!("[{pc:>width$}]").bright_black()
             )?;
+            if let Some(line) = line {
+                write!(f, "{} ", format!(":{line:<line_width$}").bright_black())?;
+            }
+            if let Some((child, up_value)) = capturing
+                && up_value < child.up_value_count as usize
+                && matches!(inst.mode, HSOpCode::Move | HSOpCode::GetUpval)
+            {
+                self.fmt_capture(f, pc, child, up_value)?;
+                capturing = Some((child, up_value + 1));
+                continue;
+            }
+            capturing = self.closure_child(pc).map(|(_, child)| (child, 0));
+            if inst.mode == HSOpCode::Unknown {
+                write!(f, "{}", format!("Unknown({:#010x})", inst.raw).red())?;
+            } else {
+                write!(f, "{}{} ", inst.mode.to_string().yellow(), ":".yellow())?;
+            }
+            // Slot accessed by a slot instruction, along with the structure of its object.
+            let slot = slot_structs[pc].zip(Self::slot_operands(inst)).and_then(
+                |(structure, (_, index))| {
+                    let position = inst.args.get(index)?.value;
+                    let slot = Self::slot_of(context.structs, inheritance, structure, position)?;
+                    Some((structure, slot))
+                },
+            );
             for (i, arg) in inst.args.iter().enumerate() {
                 if i > 0 {
                     write!(f, " ")?;
                 }
-                match arg.mode {
-                    HSOpArgMode::CONST => {
-                        write!(f, "{}", "CONST(".bright_cyan())?;
-                        #[allow(clippy::cast_sign_loss)]
-                        if let Some(constant) = self.constants.get(arg.value as usize) {
-                            write!(f, "{constant}")?;
-                        }
-                        write!(f, "{}", ")".bright_cyan())?;
-                    }
-                    _ => write!(
-                        f,
-                        "{}{}{}{}",
-                        arg.mode.to_string().bright_cyan(),
-                        "(".bright_cyan(),
-                        arg.value.to_string().bright_blue(),
-                        ")".bright_cyan()
-                    )?,
+                if i == 1 && Self::checks_type(inst) {
+                    Self::fmt_type_code(f, arg, context.structs)?;
+                    continue;
+                }
+                self.fmt_arg(f, inst, i, arg, pc, slot)?;
+            }
+            if inst.mode == HSOpCode::Closure {
+                match self.closure_child(pc) {
+                    Some((_, child)) => write!(f, " {}", format!("-> {}", child.label()).green())?,
+                    None => write!(f, " {}", "(out of range)".red())?,
+                }
+            }
+            self.fmt_data(f, pc, context.structs)?;
+            if let Some(target) = inst.jump_target(pc) {
+                write!(f, " {}", format!("-> L{target}").green())?;
+                if target >= self.instructions.len() {
+                    write!(f, " {}", "(out of range)".red())?;
                 }
             }
+            if pc > 0 && self.instructions[pc - 1].skips_next() {
+                write!(f, " {}", "(may be skipped)".bright_black())?;
+            }
             writeln!(f)?;
         }
+        Ok(())
+    }
 
-        if self.has_debug_info {
-            writeln!(f, "{}", "Debug Info:".bright_blue())?;
-            write!(f, "{}", self.debug_info)?;
+    /// Writes an argument of the instruction at `pc`. Registers holding a local and upvalues
+    /// are annotated with their names from the debug info, such as `REG(2:player)`, and the
+    /// known `slot` with its object's structure, name and type, such as
+    /// `SLOT(16:Player.health:TNUMBER)`.
+    fn fmt_arg(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        inst: &HSInstruction,
+        index: usize,
+        arg: &HSInstructionArg,
+        pc: usize,
+        slot: Option<(&HSStructPrototype, &HSStructSlot)>,
+    ) -> std::fmt::Result {
+        let is_up_value = index == 1
+            && matches!(
+                inst.mode,
+                HSOpCode::GetUpval | HSOpCode::SetUpval | HSOpCode::SetUpvalR1
+            );
+        let is_slot = Self::slot_operands(inst).is_some_and(|(_, slot)| slot == index);
+        let (mode, name) = match &arg.mode {
+            HSOpArgMode::CONST => {
+                write!(f, "{}", "CONST(".bright_cyan())?;
+                #[allow(clippy::cast_sign_loss)]
+                if let Some(constant) = self.constants.get(arg.value as usize) {
+                    write!(f, "{constant}")?;
+                }
+                return write!(f, "{}", ")".bright_cyan());
+            }
+            HSOpArgMode::REG => ("REG".to_string(), self.local_name(arg.value, pc)),
+            _ if is_up_value => ("UPVAL".to_string(), self.up_value_name(arg.value)),
+            _ if is_slot => {
+                let name = slot.map(|(structure, slot)| {
+                    format!("{}.{}:{:?}", structure.name, slot.name, slot.type_)
+                });
+                write!(
+                    f,
+                    "{}{}{}",
+                    "SLOT".bright_cyan(),
+                    "(".bright_cyan(),
+                    arg.value.to_string().bright_blue()
+                )?;
+                if let Some(name) = name {
+                    write!(f, "{}{}", ":".bright_cyan(), name.bright_green())?;
+                }
+                return write!(f, "{}", ")".bright_cyan());
+            }
+            mode @ HSOpArgMode::NUMBER => (mode.to_string(), None),
+        };
+        write!(
+            f,
+            "{}{}{}",
+            mode.bright_cyan(),
+            "(".bright_cyan(),
+            arg.value.to_string().bright_blue()
+        )?;
+        if let Some(name) = name {
+            write!(f, "{}{}", ":".bright_cyan(), name.bright_green())?;
         }
+        write!(f, "{}", ")".bright_cyan())
+    }
 
-        writeln!(f)?;
-        for func in &self.child_functions {
-            write!(f, "{func}")?;
+    /// Writes the type code of a `CheckType` as the type or structure it refers to, such as
+    /// `TSTRING` or `struct Player`, and unknown codes as numbers marked as unknown.
+    fn fmt_type_code(
+        f: &mut std::fmt::Formatter<'_>,
+        arg: &HSInstructionArg,
+        structs: &[HSStructPrototype],
+    ) -> std::fmt::Result {
+        match HSTypeCode::resolve(arg.value, structs) {
+            HSTypeCode::Unknown(code) => write!(
+                f,
+                "{}{}{} {}",
+                "NUMBER(".bright_cyan(),
+                code.to_string().bright_blue(),
+                ")".bright_cyan(),
+                "(unknown type)".red()
+            ),
+            type_code => write!(f, "{}", type_code.to_string().bright_green()),
         }
-        Ok(())
+    }
+
+    /// Writes what the instruction at `pc` means together with the `Data` extending it: the array
+    /// items a `SetList` stores and the structure a `NewStruct` creates. `Data` instructions
+    /// point back to the instruction they extend.
+    fn fmt_data(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        pc: usize,
+        structs: &[HSStructPrototype],
+    ) -> std::fmt::Result {
+        let inst = &self.instructions[pc];
+        match inst.mode {
+            HSOpCode::SetList => match self.set_list_batch(pc) {
+                Some(batch) => {
+                    let first = batch.saturating_sub(1) * FIELDS_PER_FLUSH + 1;
+                    let last = match usize::try_from(inst.args.get(1).map_or(0, |arg| arg.value)) {
+                        Ok(0) | Err(_) => String::new(),
+                        Ok(count) => (first + count - 1).to_string(),
+                    };
+                    write!(f, " {}", format!("-> [{first}..{last}]").green())
+                }
+                None => write!(f, " {}", "(missing Data)".red()),
+            },
+            HSOpCode::NewStruct => match self.new_struct_id(pc) {
+                Some(id) => match structs.iter().find(|structure| structure.id == id) {
+                    Some(structure) => {
+                        write!(f, " {}", format!("-> struct {}", structure.name).green())
+                    }
+                    None => write!(f, " {}", format!("-> struct #{id} (unknown)").red()),
+                },
+                None => Ok(()),
+            },
+            HSOpCode::Data if self.extends_previous(pc) => {
+                write!(f, " {}", format!("(extends [{}])", pc - 1).bright_black())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the name of the local held in `register` at `pc`, from the debug info.
+    fn local_name(&self, register: i32, pc: usize) -> Option<&str> {
+        if !self.has_debug_info {
+            return None;
+        }
+        let local = self
+            .debug_info
+            .local_at(usize::try_from(register).ok()?, pc)?;
+        Some(local.local_name.as_str()).filter(|name| !name.is_empty())
+    }
+
+    /// Returns the name of one of the function's upvalues, from the debug info.
+    fn up_value_name(&self, index: i32) -> Option<&str> {
+        if !self.has_debug_info {
+            return None;
+        }
+        self.debug_info.up_value_name(usize::try_from(index).ok()?)
+    }
+
+    /// Writes the `Move` or `GetUpval` at `pc` following a closure as the capture of the child's
+    /// upvalue `up_value`, from a register or from an upvalue of this function.
+    fn fmt_capture(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        pc: usize,
+        child: &HSFunction,
+        up_value: usize,
+    ) -> std::fmt::Result {
+        let source = self.instructions[pc].args.get(1).map_or(0, |arg| arg.value);
+        let (mode, name) = if self.instructions[pc].mode == HSOpCode::Move {
+            ("REG", self.local_name(source, pc))
+        } else {
+            ("UPVAL", self.up_value_name(source))
+        };
+        let source = match name {
+            Some(name) => format!("{mode}({source}:{name})"),
+            None => format!("{mode}({source})"),
+        };
+        let name = if child.has_debug_info {
+            child.debug_info.up_value_name(up_value)
+        } else {
+            None
+        }
+        .map_or_else(|| format!("upvalue {up_value}"), str::to_string);
+        writeln!(
+            f,
+            "{}{} {} {}",
+            "Capture".yellow(),
+            ":".yellow(),
+            source.bright_cyan(),
+            format!("-> {name}").green()
+        )
+    }
+}
+
+impl Display for HSFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_function(f, true, HSDisplayContext::default())
     }
 }
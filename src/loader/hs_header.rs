@@ -1,12 +1,14 @@
+use super::{hs_reader::read_int, hs_writer::write_int};
 use crate::common::errors::HkscError;
 
 use bitflags::bitflags;
-use byteorder::{BE, LE, ReadBytesExt};
+use byteorder::{BE, LE, ReadBytesExt, WriteBytesExt};
 use colored::Colorize;
-use std::{fmt::Display, fs::File, io::BufReader};
+use std::fmt::Display;
 
 bitflags! {
     #[derive(Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize), serde(transparent))]
     /// Flags for enabling `HavokScript` features, such as global memoization.
     pub struct HSFeatures: u8 {
         /// Enable memoization.
@@ -38,6 +40,7 @@ impl Display for HSFeatures {
 }
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Header of a `HavokScript` file.
 pub struct HSHeader {
     /// Magic number for script files. (0x61754C1B / "\1BLua")
@@ -67,7 +70,7 @@ pub struct HSHeader {
 }
 
 impl HSHeader {
-    pub fn read(&mut self, reader: &mut BufReader<File>) -> Result<(), HkscError> {
+    pub fn read(&mut self, reader: &mut impl ReadBytesExt) -> Result<(), HkscError> {
         self.magic = reader.read_u32::<LE>()?;
         if self.magic != 1_635_077_147 {
             return Err(HkscError::IncorrectMagicNumber(self.magic));
@@ -89,9 +92,29 @@ impl HSHeader {
         self.features = HSFeatures::from_bits_truncate(reader.read_u8()?);
         self.shared = reader.read_u8()?;
         if self.is_little_endian {
-            self.enum_count = reader.read_u32::<LE>()?;
+            self.enum_count = read_int::<LE>(reader, self)?;
         } else {
-            self.enum_count = reader.read_u32::<BE>()?;
+            self.enum_count = read_int::<BE>(reader, self)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, writer: &mut impl WriteBytesExt) -> Result<(), HkscError> {
+        writer.write_u32::<LE>(self.magic)?;
+        writer.write_u8(self.version)?;
+        writer.write_u8(self.fmt)?;
+        writer.write_u8(self.is_little_endian.into())?;
+        writer.write_u8(self.int_size)?;
+        writer.write_u8(self.t_size)?;
+        writer.write_u8(self.instruction_size)?;
+        writer.write_u8(self.number_size)?;
+        writer.write_u8(self.is_integer.into())?;
+        writer.write_u8(self.features.bits())?;
+        writer.write_u8(self.shared)?;
+        if self.is_little_endian {
+            write_int::<LE>(writer, self, self.enum_count)?;
+        } else {
+            write_int::<BE>(writer, self, self.enum_count)?;
         }
         Ok(())
     }
@@ -0,0 +1,209 @@
+use super::hs_structure::{HSStructPrototype, HSStructSlot};
+
+use std::{collections::HashMap, fmt::Display};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(tag = "type"))]
+/// Problem found while resolving the structures that structures inherit from.
+pub enum HSInheritanceIssue {
+    /// A structure inherits from a name that no structure in the file has.
+    MissingParent {
+        /// Name of the inheriting structure.
+        structure: String,
+        /// Name of the structure it inherits from.
+        parent: String,
+    },
+    /// Structures inherit from each other.
+    Cycle {
+        /// Names of the structures along the cycle, the first one being repeated at the end.
+        structures: Vec<String>,
+    },
+}
+
+impl Display for HSInheritanceIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingParent { structure, parent } => write!(
+                f,
+                "Structure {structure} inherits from missing structure {parent}!"
+            ),
+            Self::Cycle { structures } => write!(
+                f,
+                "Structures inherit from each other: {}!",
+                structures.join(" > ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// A slot of a structure's flattened slot set, along with the structure that declares it.
+pub struct HSInheritedSlot<'a> {
+    /// The slot.
+    pub slot: &'a HSStructSlot,
+    /// The structure declaring the slot, either the flattened structure or one of its ancestors.
+    pub origin: &'a HSStructPrototype,
+}
+
+impl HSInheritedSlot<'_> {
+    /// Returns whether the slot is declared by an ancestor of `structure` rather than by itself.
+    #[must_use]
+    pub fn is_inherited(&self, structure: &HSStructPrototype) -> bool {
+        !std::ptr::eq(self.origin, structure)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Inheritance graph of a file's structures, with the names in `inherited_structs` resolved.
+pub struct HSInheritance {
+    /// Indices of the structures each structure inherits from, in the order of `structs`.
+    /// Missing parents are left out.
+    pub parents: Vec<Vec<usize>>,
+    /// Missing parents and cycles found while resolving.
+    pub issues: Vec<HSInheritanceIssue>,
+}
+
+/// Visiting state of a structure while looking for cycles.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    Active,
+    Done,
+}
+
+impl HSInheritance {
+    /// Resolves the parents of `structs` by name, the first structure with a name winning when
+    /// several share it.
+    #[must_use]
+    pub fn resolve(structs: &[HSStructPrototype]) -> Self {
+        let mut indices = HashMap::new();
+        for (index, structure) in structs.iter().enumerate() {
+            indices.entry(structure.name.as_str()).or_insert(index);
+        }
+        let mut inheritance = Self::default();
+        for structure in structs {
+            let mut parents = Vec::with_capacity(structure.inherited_structs.len());
+            for parent in &structure.inherited_structs {
+                match indices.get(parent.as_str()) {
+                    Some(&index) => parents.push(index),
+                    None => inheritance.issues.push(HSInheritanceIssue::MissingParent {
+                        structure: structure.name.clone(),
+                        parent: parent.clone(),
+                    }),
+                }
+            }
+            inheritance.parents.push(parents);
+        }
+
+        let mut visits = vec![Visit::New; structs.len()];
+        for index in 0..structs.len() {
+            inheritance.find_cycles(structs, index, &mut visits);
+        }
+        inheritance
+    }
+
+    /// Depth-first search from `index`, reporting each edge back to a structure on the search path
+    /// as a cycle. The path is kept on an explicit stack, so long inheritance chains cannot
+    /// overflow the call stack.
+    fn find_cycles(&mut self, structs: &[HSStructPrototype], index: usize, visits: &mut [Visit]) {
+        if visits[index] != Visit::New {
+            return;
+        }
+        visits[index] = Visit::Active;
+        // Each entry is a structure on the path and the position of the next parent to visit.
+        let mut stack = vec![(index, 0)];
+        while let Some(&mut (current, ref mut next)) = stack.last_mut() {
+            let Some(&parent) = self.parents[current].get(*next) else {
+                stack.pop();
+                visits[current] = Visit::Done;
+                continue;
+            };
+            *next += 1;
+            match visits[parent] {
+                Visit::New => {
+                    visits[parent] = Visit::Active;
+                    stack.push((parent, 0));
+                }
+                Visit::Active => {
+                    let start = stack.iter().rposition(|&(entry, _)| entry == parent);
+                    let cycle = stack[start.unwrap_or_default()..]
+                        .iter()
+                        .map(|&(entry, _)| entry)
+                        .chain([parent])
+                        .map(|entry| structs[entry].name.clone())
+                        .collect();
+                    self.issues
+                        .push(HSInheritanceIssue::Cycle { structures: cycle });
+                }
+                Visit::Done => {}
+            }
+        }
+    }
+
+    /// Returns the indices of the structures `index` inherits from, directly or not, each parent
+    /// followed by its own ancestors. Each ancestor is listed once, even when reached through
+    /// several parents or a cycle.
+    #[must_use]
+    pub fn ancestors(&self, index: usize) -> Vec<usize> {
+        let mut ancestors = Vec::new();
+        let mut pending = self.parents.get(index).cloned().unwrap_or_default();
+        pending.reverse();
+        while let Some(parent) = pending.pop() {
+            if parent == index || ancestors.contains(&parent) {
+                continue;
+            }
+            ancestors.push(parent);
+            if let Some(parents) = self.parents.get(parent) {
+                pending.extend(parents.iter().rev());
+            }
+        }
+        ancestors
+    }
+
+    /// Returns the full slot set of the structure at `index` in `structs`, its own slots first and
+    /// then those of its ancestors, in the order of `ancestors`.
+    ///
+    /// A slot whose name is already in the set, because a nearer structure declares it again, is
+    /// left out. Ancestors that are no longer in `structs`, because it changed since resolving, are
+    /// skipped.
+    #[must_use]
+    pub fn flattened_slots<'a>(
+        &self,
+        structs: &'a [HSStructPrototype],
+        index: usize,
+    ) -> Vec<HSInheritedSlot<'a>> {
+        let mut slots: Vec<HSInheritedSlot<'a>> = Vec::new();
+        let Some(structure) = structs.get(index) else {
+            return slots;
+        };
+        for origin in std::iter::once(structure).chain(
+            self.ancestors(index)
+                .into_iter()
+                .filter_map(|index| structs.get(index)),
+        ) {
+            for slot in &origin.slots {
+                if slots.iter().all(|entry| entry.slot.name != slot.name) {
+                    slots.push(HSInheritedSlot { slot, origin });
+                }
+            }
+        }
+        slots
+    }
+
+    /// Returns the slot at `position` in the flattened slot set of the structure at `index`,
+    /// which is how slot instructions refer to slots. The structure's own slots take precedence
+    /// over inherited ones at the same position.
+    #[must_use]
+    pub fn slot_at<'a>(
+        &self,
+        structs: &'a [HSStructPrototype],
+        index: usize,
+        position: i32,
+    ) -> Option<HSInheritedSlot<'a>> {
+        let position = u64::try_from(position).ok()?;
+        self.flattened_slots(structs, index)
+            .into_iter()
+            .find(|member| member.slot.position == position)
+    }
+}
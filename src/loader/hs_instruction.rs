@@ -1,21 +1,17 @@
-use super::hs_opcodes::{
-    HSMode, HSOpArgMode, HSOpArgModeA, HSOpArgModeBC, HSOpCode, HSOpMode, OP_TABLE,
+use super::{
+    hs_encoding::{HSField, HSInstructionEncoding},
+    hs_opcodes::{HSMode, HSOpArgMode, HSOpArgModeA, HSOpArgModeBC, HSOpCode, HSOpMode},
+    hs_profile::HSOpcodeProfile,
+};
+use crate::common::{
+    errors::HkscError,
+    extensions::{BufReaderExt, Readable, Writable, WriterExt},
 };
-use crate::common::{errors::HkscError, extensions::BufReaderExt, extensions::Readable};
-
-use byteorder::{ByteOrder, ReadBytesExt};
 
-// Bit masks for instruction parsing
-const OPCODE_MASK: u32 = 0x7F << 25; // Highest 7 bits
-const A_ARG_MASK: i32 = 0xFF; // Lowest 8 bits
-const B_ARG_MASK: i32 = 0xFF << 17; // Bits 17-24
-const B_ARG_EXTMASK: i32 = 0x1FF << 17; // Bits 17-25 (including extension)
-const C_ARG_MASK: i32 = 0xFF << 8; // Bits 8-15
-const C_ARG_EXTMASK: i32 = 0x1FF << 8; // Bits 8-16 (including extension)
-const NON_ABC_B_MASK: i32 = 0x1FFFF << 8; // Bits 8-25 for non-ABC formats
-const REG_CONST_THRESHOLD: i32 = 0x100; // Values >= 256 indicate constants
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Represents a single argument for a `HavokScript` instruction. Each argument has both
 /// a mode (indicating how it should be interpreted) and a raw value.
 pub struct HSInstructionArg {
@@ -26,6 +22,7 @@ pub struct HSInstructionArg {
 }
 
 #[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Represents a single instruction in the `HavokScript` bytecode.
 /// Each instruction consists of an opcode and up to three arguments.
 pub struct HSInstruction {
@@ -35,40 +32,279 @@ pub struct HSInstruction {
     /// Vector containing the instruction's arguments (typically 0-3 arguments).
     /// The meaning and number of arguments depends on the opcode.
     pub args: Vec<HSInstructionArg>,
+    /// The raw instruction word this instruction was decoded from.
+    /// Bits that none of the arguments describe are preserved from here when encoding.
+    pub raw: u64,
 }
 
 impl Readable for HSInstruction {
-    /// Reads and decodes a single instruction from the bytecode stream.
-    /// `HavokScript` instructions are encoded as 32-bit integers in big-endian format.
+    /// Reads and decodes a single instruction from the bytecode stream, using the built-in
+    /// opcode profile.
     fn read<T: ByteOrder>(&mut self, reader: &mut impl BufReaderExt) -> Result<(), HkscError> {
-        let raw = reader.read_i32::<T>()?;
-        #[allow(clippy::cast_sign_loss)]
-        let op_entry = &OP_TABLE[((raw as u32 & OPCODE_MASK) >> 25) as usize];
+        self.read_with_profile::<T>(reader, HSOpcodeProfile::builtin())
+    }
+}
 
-        self.mode = op_entry.op_code.clone();
-        self.read_op_a(raw, op_entry);
-        self.read_op_bc(raw, op_entry);
-        Ok(())
+impl Writable for HSInstruction {
+    /// Encodes and writes the instruction with the built-in opcode profile and the standard
+    /// encoding.
+    fn write<T: ByteOrder>(&self, writer: &mut impl WriterExt) -> Result<(), HkscError> {
+        self.write_with_encoding::<T>(
+            writer,
+            HSOpcodeProfile::builtin(),
+            &HSInstructionEncoding::STANDARD,
+        )
     }
 }
 
 impl HSInstruction {
+    /// Creates an instruction from an opcode and its arguments, encoding it from scratch.
+    #[must_use]
+    pub fn new(mode: HSOpCode, args: Vec<HSInstructionArg>) -> Self {
+        let mut instruction = Self { mode, args, raw: 0 };
+        instruction.raw = instruction.encode();
+        instruction
+    }
+
+    /// Reads and decodes a single instruction from the bytecode stream.
+    /// `HavokScript` instructions are encoded as 32-bit integers in big-endian format.
+    /// Instructions whose opcode number isn't mapped by `profile` fail with `HkscError::UnknownOpcode`.
+    pub fn read_with_profile<T: ByteOrder>(
+        &mut self,
+        reader: &mut impl BufReaderExt,
+        profile: &HSOpcodeProfile,
+    ) -> Result<(), HkscError> {
+        self.read_with_encoding::<T>(reader, profile, &HSInstructionEncoding::STANDARD)
+    }
+
+    /// Reads and decodes a single 4 byte instruction word, laid out as described by `encoding`.
+    /// Other sizes fail with `HkscError::UnsupportedInstructionSize`.
+    pub fn read_with_encoding<T: ByteOrder>(
+        &mut self,
+        reader: &mut impl BufReaderExt,
+        profile: &HSOpcodeProfile,
+        encoding: &HSInstructionEncoding,
+    ) -> Result<(), HkscError> {
+        if encoding.size != 4 {
+            return Err(HkscError::UnsupportedInstructionSize(encoding.size));
+        }
+        self.raw = reader.read_u32::<T>()?.into();
+        let opcode = u8::try_from(encoding.opcode.get(self.raw)).unwrap_or(u8::MAX);
+        let Some(op_entry) = profile.mode(opcode) else {
+            // The offset is only needed for the error, so it isn't tracked for every instruction.
+            let offset = reader.stream_position()? - u64::from(encoding.size);
+            return Err(HkscError::UnknownOpcode {
+                offset,
+                raw: self.raw,
+                opcode,
+            });
+        };
+
+        self.mode = op_entry.op_code;
+        self.read_op_a(encoding, op_entry);
+        self.read_op_bc(encoding, op_entry);
+        Ok(())
+    }
+
+    /// Reads an instruction like `read_with_encoding`, but keeps instructions with an unknown opcode
+    /// as `HSOpCode::Unknown` so that the rest of the function can still be read.
+    pub fn read_lenient<T: ByteOrder>(
+        &mut self,
+        reader: &mut impl BufReaderExt,
+        profile: &HSOpcodeProfile,
+        encoding: &HSInstructionEncoding,
+    ) -> Result<(), HkscError> {
+        match self.read_with_encoding::<T>(reader, profile, encoding) {
+            Err(HkscError::UnknownOpcode { raw, .. }) => {
+                *self = Self {
+                    mode: HSOpCode::Unknown,
+                    args: Vec::new(),
+                    raw,
+                };
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Returns the index of the instruction a `Jmp`, `ForPrep` or `ForLoop` at `pc` jumps to.
+    ///
+    /// Targets are relative to the following instruction. `None` is returned for other
+    /// instructions and for jumps landing before the start of the function.
+    #[must_use]
+    pub fn jump_target(&self, pc: usize) -> Option<usize> {
+        match self.mode {
+            HSOpCode::Jmp | HSOpCode::ForPrep | HSOpCode::ForLoop => {
+                let offset = self.args.get(1).map_or(0, |arg| arg.value);
+                let target = i64::try_from(pc).ok()? + 1 + i64::from(offset);
+                usize::try_from(target).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns whether this instruction may skip the instruction following it, which is the
+    /// case for comparisons, tests and `TForLoop`.
+    #[must_use]
+    pub fn skips_next(&self) -> bool {
+        matches!(
+            self.mode,
+            HSOpCode::Eq
+                | HSOpCode::EqBk
+                | HSOpCode::Lt
+                | HSOpCode::LtBk
+                | HSOpCode::Le
+                | HSOpCode::LeBk
+                | HSOpCode::Test
+                | HSOpCode::TestR1
+                | HSOpCode::TestSet
+                | HSOpCode::TForLoop
+        )
+    }
+
+    /// Encodes the instruction back into a 32-bit instruction word, using the numbering of `OP_TABLE`.
+    ///
+    /// This is the inverse of decoding: arguments are placed back into their fields
+    /// according to the opcode's modes, and all remaining bits are taken from `raw`.
+    /// Instructions that were read and left unmodified therefore encode to their original word.
+    /// Instructions with an unknown opcode encode to their `raw` word as-is.
+    #[must_use]
+    pub fn encode(&self) -> u64 {
+        // The built-in profile maps every opcode, so only `Unknown` instructions aren't encoded.
+        self.encode_with(HSOpcodeProfile::builtin(), &HSInstructionEncoding::STANDARD)
+            .unwrap_or(self.raw)
+    }
+
+    /// Encodes the instruction back into an instruction word laid out as described by `encoding`,
+    /// with the opcode number and argument modes of `profile`.
+    ///
+    /// The opcode number in `raw` is kept when `profile` maps it to the instruction's opcode, and
+    /// otherwise the lowest number mapping to it is used. Fails with `HkscError::UnmappedOpcode`
+    /// when `profile` has no number for the opcode.
+    pub fn encode_with(
+        &self,
+        profile: &HSOpcodeProfile,
+        encoding: &HSInstructionEncoding,
+    ) -> Result<u64, HkscError> {
+        if self.mode == HSOpCode::Unknown {
+            return Ok(self.raw);
+        }
+        // Profiles may map several numbers to an opcode, in which case the one the instruction
+        // was read with is kept.
+        let Some((number, op_entry)) = u8::try_from(encoding.opcode.get(self.raw))
+            .ok()
+            .filter(|&number| {
+                profile
+                    .mode(number)
+                    .is_some_and(|mode| mode.op_code == self.mode)
+            })
+            .or_else(|| profile.number(self.mode))
+            .and_then(|number| Some((number, profile.mode(number)?)))
+        else {
+            return Err(HkscError::UnmappedOpcode {
+                opcode: self.mode.to_string(),
+                profile: profile.name.clone(),
+            });
+        };
+        let mut args = self.args.iter();
+        let mut raw = self.raw;
+
+        if let Some(arg) = args.next() {
+            raw = encoding.a.set(raw, arg.value);
+        }
+        if op_entry.op_mode_b != HSOpArgModeBC::UNUSED
+            && let Some(arg) = args.next()
+        {
+            raw = if op_entry.op_mode == HSOpMode::ABC {
+                Self::write_op_abc_bc(raw, arg, op_entry.op_mode_b, encoding.b)
+            } else {
+                Self::write_op_non_abc_b(raw, arg, op_entry, encoding)
+            };
+        }
+        if op_entry.op_mode == HSOpMode::ABC
+            && op_entry.op_mode_c != HSOpArgModeBC::UNUSED
+            && let Some(arg) = args.next()
+        {
+            raw = Self::write_op_abc_bc(raw, arg, op_entry.op_mode_c, encoding.c);
+        }
+
+        // The opcode is placed last, as the lowest opcode bit doubles as the extension bit of B.
+        Ok(encoding.opcode.set(raw, i32::from(number)))
+    }
+
+    /// Encodes and writes the instruction as a 4 byte word, numbering its opcode with `profile`.
+    /// Other sizes fail with `HkscError::UnsupportedInstructionSize`.
+    pub fn write_with_encoding<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        profile: &HSOpcodeProfile,
+        encoding: &HSInstructionEncoding,
+    ) -> Result<(), HkscError> {
+        if encoding.size != 4 {
+            return Err(HkscError::UnsupportedInstructionSize(encoding.size));
+        }
+        let raw = self.encode_with(profile, encoding)?;
+        writer.write_u32::<T>(u32::try_from(raw)?)?;
+        Ok(())
+    }
+
+    /// Places a 'B' or 'C' argument of an ABC format instruction back into the raw instruction,
+    /// `field` being the argument's field.
+    fn write_op_abc_bc(
+        raw: u64,
+        arg: &HSInstructionArg,
+        mode: HSOpArgModeBC,
+        field: HSField,
+    ) -> u64 {
+        match mode {
+            HSOpArgModeBC::NUMBER | HSOpArgModeBC::REG | HSOpArgModeBC::CONST => {
+                field.set(raw, arg.value)
+            }
+            HSOpArgModeBC::REGCONST if arg.mode == HSOpArgMode::CONST => field
+                .extended()
+                .set(raw, arg.value | Self::constant_threshold(field)),
+            HSOpArgModeBC::OFFSET | HSOpArgModeBC::REGCONST => field.extended().set(raw, arg.value),
+            HSOpArgModeBC::UNUSED => raw,
+        }
+    }
+
+    /// Places the 'B' argument of a non-ABC format instruction back into the raw instruction,
+    /// undoing the `AsBx` adjustment if needed.
+    fn write_op_non_abc_b(
+        raw: u64,
+        arg: &HSInstructionArg,
+        modes: &HSMode,
+        encoding: &HSInstructionEncoding,
+    ) -> u64 {
+        let mut value = arg.value;
+        if modes.op_mode == HSOpMode::ASBX {
+            value = value.wrapping_add(encoding.sbx_bias());
+        }
+        encoding.bx.set(raw, value)
+    }
+
+    /// Returns the value at and above which an extended 'B' or 'C' argument refers to a constant
+    /// instead of a register, which is the extension bit of `field`.
+    fn constant_threshold(field: HSField) -> i32 {
+        1 << field.width
+    }
+
     /// Reads the 'A' argument from the raw instruction data.
     /// The A argument is always stored in the lowest 8 bits of the instruction.
     /// This argument typically represents the destination register for operations.
-    fn read_op_a(&mut self, raw: i32, modes: &HSMode) {
+    fn read_op_a(&mut self, encoding: &HSInstructionEncoding, modes: &HSMode) {
         let mode = if modes.op_mode_a == HSOpArgModeA::UNUSED {
             HSOpArgMode::NUMBER
         } else {
             HSOpArgMode::REG
         };
 
-        let value = raw & A_ARG_MASK;
+        let value = encoding.a.get(self.raw);
         self.args.push(HSInstructionArg { mode, value });
     }
 
-    /// Reads the 'B' argument for instructions that use the ABC instruction format.
-    /// The B argument is located in bits 17-25 of the instruction.
+    /// Reads a 'B' or 'C' argument for instructions that use the ABC instruction format.
+    /// The B argument is located in bits 17-25 of the instruction, and the C argument in bits 8-16.
     ///
     /// Argument interpretation varies based on mode:
     /// * REGCONST: Value can represent either a register (<256) or constant (≥256)
@@ -76,50 +312,22 @@ impl HSInstruction {
     /// * REG: Represents a register index
     /// * CONST: Represents a constant pool index
     /// * NUMBER: Raw numeric value
-    fn read_op_abc_b(&mut self, raw: i32, modes: &HSMode) {
-        let (mode, value) = match modes.op_mode_b {
-            HSOpArgModeBC::NUMBER => (HSOpArgMode::NUMBER, (raw & B_ARG_MASK) >> 17),
-            HSOpArgModeBC::OFFSET => (HSOpArgMode::NUMBER, (raw & B_ARG_EXTMASK) >> 17),
-            HSOpArgModeBC::REG => (HSOpArgMode::REG, (raw & B_ARG_MASK) >> 17),
+    fn read_op_abc_bc(&mut self, field: HSField, mode: HSOpArgModeBC) {
+        let (mode, value) = match mode {
+            HSOpArgModeBC::NUMBER => (HSOpArgMode::NUMBER, field.get(self.raw)),
+            HSOpArgModeBC::OFFSET => (HSOpArgMode::NUMBER, field.extended().get(self.raw)),
+            HSOpArgModeBC::REG => (HSOpArgMode::REG, field.get(self.raw)),
             HSOpArgModeBC::REGCONST => {
-                let value = (raw & B_ARG_EXTMASK) >> 17;
-                if value < REG_CONST_THRESHOLD {
+                let value = field.extended().get(self.raw);
+                if value < Self::constant_threshold(field) {
                     // Values below 256 indicate a register reference
                     (HSOpArgMode::REG, value)
                 } else {
                     // Values 256 and above indicate a constant reference
-                    (HSOpArgMode::CONST, value & 0xFF)
-                }
-            }
-            HSOpArgModeBC::CONST => (HSOpArgMode::CONST, (raw >> 17) & 0xFF),
-            HSOpArgModeBC::UNUSED => (HSOpArgMode::CONST, 0),
-        };
-        self.args.push(HSInstructionArg { mode, value });
-    }
-
-    /// Reads the 'C' argument for instructions using the ABC instruction format.
-    /// The C argument is located in bits 8-16 of the instruction.
-    ///
-    /// Interpretation follows the same rules as the B argument:
-    /// * REGCONST: Value represents register (<256) or constant (≥256)
-    /// * OFFSET: Used for relative jumps
-    /// * REG: Register index
-    /// * CONST: Constant pool index
-    /// * NUMBER: Raw numeric value
-    fn read_op_abc_c(&mut self, raw: i32, modes: &HSMode) {
-        let (mode, value) = match modes.op_mode_c {
-            HSOpArgModeBC::NUMBER => (HSOpArgMode::NUMBER, (raw & C_ARG_MASK) >> 8),
-            HSOpArgModeBC::OFFSET => (HSOpArgMode::NUMBER, (raw & C_ARG_EXTMASK) >> 8),
-            HSOpArgModeBC::REG => (HSOpArgMode::REG, (raw & C_ARG_MASK) >> 8),
-            HSOpArgModeBC::REGCONST => {
-                let value = (raw & C_ARG_EXTMASK) >> 8;
-                if value < REG_CONST_THRESHOLD {
-                    (HSOpArgMode::REG, value)
-                } else {
-                    (HSOpArgMode::CONST, value & 0xFF)
+                    (HSOpArgMode::CONST, field.get(self.raw))
                 }
             }
-            HSOpArgModeBC::CONST => (HSOpArgMode::CONST, (raw >> 8) & 0xFF),
+            HSOpArgModeBC::CONST => (HSOpArgMode::CONST, field.get(self.raw)),
             HSOpArgModeBC::UNUSED => (HSOpArgMode::CONST, 0),
         };
         self.args.push(HSInstructionArg { mode, value });
@@ -128,8 +336,8 @@ impl HSInstruction {
     /// Reads the 'B' argument for non-ABC instruction formats (like `AsBx`).
     /// For these formats, the B argument spans bits 8-25 and is treated as
     /// a single field rather than two separate arguments.
-    fn read_op_non_abc_b(&mut self, raw: i32, modes: &HSMode) {
-        let mut value = (raw & NON_ABC_B_MASK) >> 8;
+    fn read_op_non_abc_b(&mut self, encoding: &HSInstructionEncoding, modes: &HSMode) {
+        let mut value = encoding.bx.get(self.raw);
         let mode = match modes.op_mode_b {
             HSOpArgModeBC::NUMBER | HSOpArgModeBC::OFFSET => HSOpArgMode::NUMBER,
             HSOpArgModeBC::CONST => HSOpArgMode::CONST,
@@ -139,7 +347,7 @@ impl HSInstruction {
         // For AsBx format instructions, adjust the value by subtracting 0xFFFF
         // to support negative offsets
         if modes.op_mode == HSOpMode::ASBX {
-            value = value.wrapping_sub(0xFFFF);
+            value = value.wrapping_sub(encoding.sbx_bias());
         }
         self.args.push(HSInstructionArg { mode, value });
     }
@@ -148,17 +356,17 @@ impl HSInstruction {
     /// Different instruction formats interpret these bits differently:
     /// * ABC format: Bits are split into two separate arguments (B and C)
     /// * Other formats: Bits are treated as a single larger argument (B only)
-    fn read_op_bc(&mut self, raw: i32, modes: &HSMode) {
+    fn read_op_bc(&mut self, encoding: &HSInstructionEncoding, modes: &HSMode) {
         if modes.op_mode_b != HSOpArgModeBC::UNUSED {
             if modes.op_mode == HSOpMode::ABC {
-                self.read_op_abc_b(raw, modes);
+                self.read_op_abc_bc(encoding.b, modes.op_mode_b);
             } else {
-                self.read_op_non_abc_b(raw, modes);
+                self.read_op_non_abc_b(encoding, modes);
             }
         }
 
         if modes.op_mode == HSOpMode::ABC && modes.op_mode_c != HSOpArgModeBC::UNUSED {
-            self.read_op_abc_c(raw, modes);
+            self.read_op_abc_bc(encoding.c, modes.op_mode_c);
         }
     }
 }
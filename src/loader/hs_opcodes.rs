@@ -1,4 +1,4 @@
-use num_enum::TryFromPrimitive;
+use num_enum::{IntoPrimitive, TryFromPrimitive};
 
 /// Small helper macro to create a `HSMode` struct.
 macro_rules! hs_mode {
@@ -14,7 +14,8 @@ macro_rules! hs_mode {
 }
 
 /// Enum representing `HavokScript` operation codes
-#[derive(Debug, TryFromPrimitive, Clone, Default)]
+#[derive(Debug, TryFromPrimitive, IntoPrimitive, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum HSOpCode {
     #[default]
@@ -111,6 +112,9 @@ pub enum HSOpCode {
     GetSlotD,
     GetGlobalMem,
     NumOpcodes,
+    /// Opcode outside of `OP_TABLE`, only produced when reading leniently.
+    /// The instruction keeps its undecoded word in `HSInstruction::raw`.
+    Unknown,
 }
 
 impl std::fmt::Display for HSOpCode {
@@ -120,7 +124,8 @@ impl std::fmt::Display for HSOpCode {
 }
 
 /// Enum representing `HavokScript` data types
-#[derive(Debug, TryFromPrimitive, Clone, Default)]
+#[derive(Debug, TryFromPrimitive, IntoPrimitive, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(u8)]
 pub enum HSType {
     #[default]
@@ -141,6 +146,7 @@ pub enum HSType {
 
 /// Enum representing argument modes for `HavokScript` operations
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum HSOpArgMode {
     #[default]
     NUMBER,
@@ -155,14 +161,16 @@ impl std::fmt::Display for HSOpArgMode {
 }
 
 /// Enum representing argument modes for the A field in `HavokScript` operations
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub enum HSOpArgModeA {
     UNUSED,
     REG,
 }
 
 /// Enum representing operation modes in `HavokScript`
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub enum HSOpMode {
     ABC,  // Operation with three fields: A, B, and C
     ABX,  // Operation with two fields: A and BX
@@ -170,7 +178,8 @@ pub enum HSOpMode {
 }
 
 /// Enum representing argument modes for B and C fields in `HavokScript` operations
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub enum HSOpArgModeBC {
     UNUSED,   // Unused argument
     NUMBER,   // Argument is a number
@@ -181,6 +190,7 @@ pub enum HSOpArgModeBC {
 }
 
 /// Struct representing a complete `HavokScript` operation mode
+#[derive(Debug, Clone)]
 pub struct HSMode {
     pub op_code: HSOpCode,
     pub op_mode: HSOpMode,
@@ -0,0 +1,30 @@
+use super::{hs_encoding::HSInstructionEncoding, hs_header::HSHeader, hs_profile::HSOpcodeProfile};
+use crate::common::errors::HkscError;
+
+/// Options that change how a `HavokScriptFile` is read.
+#[derive(Debug, Clone, Default)]
+pub struct HSReadOptions {
+    /// Whether structures use the inheritance extension, which adds a list of parent
+    /// structure names to every structure prototype. `None` detects it from the structures.
+    pub enable_inheritance: Option<bool>,
+    /// Whether instructions with an unknown opcode are kept as `HSOpCode::Unknown`
+    /// instead of stopping the read with `HkscError::UnknownOpcode`.
+    pub lenient: bool,
+    /// Opcode numbering and argument modes used to decode instructions.
+    pub profile: HSOpcodeProfile,
+    /// Layout of instruction words, `None` using the one for the header's instruction size.
+    pub encoding: Option<HSInstructionEncoding>,
+    /// Whether a failed read returns everything read up to the failure, with the failure and
+    /// the skipped sections listed in `HavokScriptFile::diagnostics`, instead of an error.
+    pub recover: bool,
+}
+
+impl HSReadOptions {
+    /// Returns the encoding to decode the instructions of a file with `header` with.
+    pub fn encoding(&self, header: &HSHeader) -> Result<HSInstructionEncoding, HkscError> {
+        match self.encoding {
+            Some(encoding) => Ok(encoding),
+            None => HSInstructionEncoding::for_size(header.instruction_size),
+        }
+    }
+}
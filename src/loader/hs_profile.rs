@@ -0,0 +1,207 @@
+//! Opcode profiles, mapping the opcode numbers found in instruction words to opcodes and their
+//! argument modes.
+//!
+//! Games built on `HavokScript` don't always share the numbering of `OP_TABLE`, so instructions
+//! are decoded through a profile. Besides the built-in presets, profiles can be loaded from
+//! TOML or JSON files when the `serde` feature is enabled:
+//! ```toml
+//! name = "my-game"
+//! # Optional preset the listed opcodes are applied on top of.
+//! base = "hks"
+//!
+//! [[opcodes]]
+//! number = 0
+//! name = "Move"
+//!
+//! [[opcodes]]
+//! number = 7
+//! name = "GetField"
+//! # Modes default to the ones of the built-in opcode with the same name.
+//! mode = "ABC"
+//! a = "REG"
+//! b = "REG"
+//! c = "CONST"
+//! ```
+
+use super::hs_opcodes::{HSMode, HSOpCode, OP_TABLE};
+#[cfg(feature = "serde")]
+use crate::common::errors::HkscError;
+
+use std::sync::LazyLock;
+
+/// Number of opcodes that fit in the 7 opcode bits of an instruction word.
+const OPCODE_COUNT: usize = 128;
+
+/// Names of the built-in presets, the first one being the default.
+pub const PRESETS: [&str; 1] = ["hks"];
+
+static BUILTIN: LazyLock<HSOpcodeProfile> = LazyLock::new(|| HSOpcodeProfile {
+    name: PRESETS[0].to_string(),
+    table: (0..OPCODE_COUNT)
+        .map(|number| OP_TABLE.get(number).cloned())
+        .collect(),
+});
+
+#[derive(Debug, Clone)]
+/// Mapping of opcode numbers to the opcode and argument modes used to decode them.
+pub struct HSOpcodeProfile {
+    /// Name of the profile.
+    pub name: String,
+    /// Modes of each opcode number, `None` for numbers that don't map to an opcode.
+    pub table: Vec<Option<HSMode>>,
+}
+
+impl Default for HSOpcodeProfile {
+    fn default() -> Self {
+        Self::builtin().clone()
+    }
+}
+
+impl HSOpcodeProfile {
+    /// Returns the profile matching `OP_TABLE`.
+    #[must_use]
+    pub fn builtin() -> &'static Self {
+        &BUILTIN
+    }
+
+    /// Returns the built-in preset with the given name.
+    #[must_use]
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "hks" => Some(Self::builtin().clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the modes of an opcode number, if it maps to an opcode.
+    #[must_use]
+    pub fn mode(&self, number: u8) -> Option<&HSMode> {
+        self.table.get(usize::from(number))?.as_ref()
+    }
+
+    /// Returns the number an opcode is encoded with, the lowest one when several map to it.
+    #[must_use]
+    pub fn number(&self, op_code: HSOpCode) -> Option<u8> {
+        let number = self
+            .table
+            .iter()
+            .position(|mode| mode.as_ref().is_some_and(|mode| mode.op_code == op_code))?;
+        u8::try_from(number).ok()
+    }
+
+    /// Loads a profile from a file, parsed as JSON when its extension is `json` and as TOML otherwise.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &std::path::Path) -> Result<Self, HkscError> {
+        let source = std::fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Self::from_json(&source)
+        } else {
+            Self::from_toml(&source)
+        }
+    }
+
+    /// Parses a profile from TOML.
+    #[cfg(feature = "serde")]
+    pub fn from_toml(source: &str) -> Result<Self, HkscError> {
+        toml::from_str::<file::ProfileFile>(source)
+            .map_err(|error| HkscError::InvalidOpcodeProfile(error.message().to_string()))?
+            .resolve()
+    }
+
+    /// Parses a profile from JSON.
+    #[cfg(feature = "serde")]
+    pub fn from_json(source: &str) -> Result<Self, HkscError> {
+        serde_json::from_str::<file::ProfileFile>(source)
+            .map_err(|error| HkscError::InvalidOpcodeProfile(error.to_string()))?
+            .resolve()
+    }
+}
+
+#[cfg(feature = "serde")]
+/// On-disk layout of a profile.
+mod file {
+    use super::{HSOpcodeProfile, OP_TABLE, OPCODE_COUNT};
+    use crate::{
+        common::errors::HkscError,
+        loader::hs_opcodes::{HSMode, HSOpArgModeA, HSOpArgModeBC, HSOpCode, HSOpMode},
+    };
+
+    #[derive(serde::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct ProfileFile {
+        name: Option<String>,
+        base: Option<String>,
+        #[serde(default)]
+        opcodes: Vec<ProfileOpcode>,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct ProfileOpcode {
+        number: u8,
+        name: HSOpCode,
+        mode: Option<HSOpMode>,
+        a: Option<HSOpArgModeA>,
+        b: Option<HSOpArgModeBC>,
+        c: Option<HSOpArgModeBC>,
+    }
+
+    impl ProfileFile {
+        pub fn resolve(self) -> Result<HSOpcodeProfile, HkscError> {
+            let mut profile = match &self.base {
+                Some(base) => HSOpcodeProfile::preset(base).ok_or_else(|| {
+                    HkscError::InvalidOpcodeProfile(format!("unknown base preset `{base}`"))
+                })?,
+                None => HSOpcodeProfile {
+                    name: String::new(),
+                    table: vec![None; OPCODE_COUNT],
+                },
+            };
+            profile.name = self.name.unwrap_or_else(|| "custom".to_string());
+
+            for opcode in self.opcodes {
+                let number = usize::from(opcode.number);
+                if number >= OPCODE_COUNT {
+                    return Err(HkscError::InvalidOpcodeProfile(format!(
+                        "opcode number {number} doesn't fit in 7 bits"
+                    )));
+                }
+                if opcode.name == HSOpCode::Unknown {
+                    return Err(HkscError::InvalidOpcodeProfile(format!(
+                        "opcode number {number} can't be mapped to Unknown"
+                    )));
+                }
+                let builtin = OP_TABLE.iter().find(|mode| mode.op_code == opcode.name);
+                let missing = || {
+                    HkscError::InvalidOpcodeProfile(format!(
+                        "{} has no built-in modes, so all of `mode`, `a`, `b` and `c` are required",
+                        opcode.name
+                    ))
+                };
+                profile.table[number] = Some(HSMode {
+                    op_code: opcode.name,
+                    op_mode: opcode
+                        .mode
+                        .or(builtin.map(|mode| mode.op_mode))
+                        .ok_or_else(missing)?,
+                    op_mode_a: opcode
+                        .a
+                        .or(builtin.map(|mode| mode.op_mode_a))
+                        .ok_or_else(missing)?,
+                    op_mode_b: opcode
+                        .b
+                        .or(builtin.map(|mode| mode.op_mode_b))
+                        .ok_or_else(missing)?,
+                    op_mode_c: opcode
+                        .c
+                        .or(builtin.map(|mode| mode.op_mode_c))
+                        .ok_or_else(missing)?,
+                });
+            }
+            Ok(profile)
+        }
+    }
+}
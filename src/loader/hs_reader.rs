@@ -25,6 +25,50 @@ pub fn read_number<T: ByteOrder>(
     }
 }
 
+/// Reads an integer from the provided reader based on the integer size specified in the header.
+///
+/// # Arguments
+///
+/// * `reader` - A mutable reference to an object that implements the `ReadBytesExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the integer size.
+///
+/// # Returns
+///
+/// * `Ok(u32)` - If the integer is successfully read and fits in a `u32`.
+/// * `Err(HkscError::InvalidIntSize)` - If the integer size specified in the header is invalid.
+pub fn read_int<T: ByteOrder>(
+    reader: &mut impl ReadBytesExt,
+    header: &HSHeader,
+) -> Result<u32, HkscError> {
+    match header.int_size {
+        4 => Ok(reader.read_u32::<T>()?),
+        8 => Ok(u32::try_from(reader.read_u64::<T>()?)?),
+        _ => Err(HkscError::InvalidIntSize(header.int_size)),
+    }
+}
+
+/// Reads a size from the provided reader based on the type size specified in the header.
+///
+/// # Arguments
+///
+/// * `reader` - A mutable reference to an object that implements the `ReadBytesExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the type size.
+///
+/// # Returns
+///
+/// * `Ok(u64)` - If the size is successfully read.
+/// * `Err(HkscError::InvalidTypeSize)` - If the type size specified in the header is invalid.
+pub fn read_size<T: ByteOrder>(
+    reader: &mut impl ReadBytesExt,
+    header: &HSHeader,
+) -> Result<u64, HkscError> {
+    match header.t_size {
+        4 => Ok(reader.read_u32::<T>()?.into()),
+        8 => Ok(reader.read_u64::<T>()?),
+        _ => Err(HkscError::InvalidTypeSize(header.t_size)),
+    }
+}
+
 /// Reads a string from the provided reader based on the string size specified in the header.
 ///
 /// # Arguments
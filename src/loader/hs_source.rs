@@ -0,0 +1,82 @@
+use super::hs_function::HSFunction;
+
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Default)]
+/// Original Lua sources of a file's functions, shown above the instructions they compiled to.
+///
+/// Sources are found from the chunk names in debug info, such as `@scripts/player.lua`, inside a
+/// root directory. A chunk name that doesn't exist below the root is also looked up by its file
+/// name alone, as scripts are often compiled from a different working directory.
+pub struct HSSources {
+    /// Lines of each chunk name, `None` when its source couldn't be found.
+    files: HashMap<String, Option<Vec<String>>>,
+}
+
+impl HSSources {
+    /// Loads the sources of `function` and all of its children from `root`.
+    #[must_use]
+    pub fn load(root: &Path, function: &HSFunction) -> Self {
+        let mut sources = Self::default();
+        sources.load_function(root, function);
+        sources
+    }
+
+    fn load_function(&mut self, root: &Path, function: &HSFunction) {
+        if function.has_debug_info && !self.files.contains_key(&function.debug_info.path) {
+            let lines = Self::resolve(root, &function.debug_info.path)
+                .and_then(|path| std::fs::read_to_string(path).ok())
+                .map(|source| source.lines().map(str::to_string).collect());
+            self.files.insert(function.debug_info.path.clone(), lines);
+        }
+        for child in &function.child_functions {
+            self.load_function(root, child);
+        }
+    }
+
+    /// Returns the path of the source of a chunk name below `root`, if it exists.
+    ///
+    /// Chunk names come from the file being read, so absolute names and names with `..` are
+    /// only looked up by their file name, which can't leave `root`.
+    fn resolve(root: &Path, chunk_name: &str) -> Option<PathBuf> {
+        // `@` marks chunks loaded from a file, `=` chunks named by the host.
+        let name = chunk_name.trim_start_matches(['@', '=']);
+        if name.is_empty() {
+            return None;
+        }
+        let relative = Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        let path = root.join(name);
+        if relative && path.is_file() {
+            return Some(path);
+        }
+        let path = root.join(Path::new(name).file_name()?);
+        path.is_file().then_some(path)
+    }
+
+    /// Returns the text of a 1-based source line.
+    #[must_use]
+    pub fn line(&self, chunk_name: &str, line: u32) -> Option<&str> {
+        let lines = self.files.get(chunk_name)?.as_ref()?;
+        lines
+            .get(usize::try_from(line).ok()?.checked_sub(1)?)
+            .map(String::as_str)
+    }
+
+    /// Returns the chunk names whose source couldn't be found.
+    #[must_use]
+    pub fn missing(&self) -> Vec<&str> {
+        let mut missing = self
+            .files
+            .iter()
+            .filter(|(_, lines)| lines.is_none())
+            .map(|(chunk_name, _)| chunk_name.as_str())
+            .collect::<Vec<_>>();
+        missing.sort_unstable();
+        missing
+    }
+}
@@ -1,14 +1,20 @@
-use super::{hs_header::HSHeader, hs_opcodes::HSType, hs_reader::read_string};
+use super::{
+    hs_header::HSHeader,
+    hs_opcodes::HSType,
+    hs_reader::{read_int, read_size, read_string},
+    hs_writer::{write_int, write_size, write_string},
+};
 use crate::{
     common::errors::HkscError,
-    common::extensions::{BufReaderExt, HeaderReadable},
+    common::extensions::{BufReaderExt, HeaderReadable, HeaderWritable, WriterExt},
 };
 
-use byteorder::{ByteOrder, ReadBytesExt};
+use byteorder::ByteOrder;
 use colored::Colorize;
 use std::fmt::Display;
 
 #[derive(Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// Information about a slot in a structure, containing type and metadata
 pub struct HSStructSlot {
     /// Name of the struct slot
@@ -16,6 +22,7 @@ pub struct HSStructSlot {
     /// Unique identifier for this struct within the file
     pub struct_id: u64,
     /// Type of the struct slot
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub type_: HSType,
     /// Reserved for VM
     pub reserved: u32,
@@ -30,11 +37,26 @@ impl HeaderReadable for HSStructSlot {
         header: &HSHeader,
     ) -> Result<(), HkscError> {
         self.name = read_string::<T>(reader, header)?;
-        self.struct_id = reader.read_u64::<T>()?;
-        let type_byte = u8::try_from(reader.read_u32::<T>()?)?;
+        self.struct_id = read_size::<T>(reader, header)?;
+        let type_byte = u8::try_from(read_int::<T>(reader, header)?)?;
         self.type_ = HSType::try_from(type_byte).map_err(|_| HkscError::UnknownType(type_byte))?;
-        self.reserved = reader.read_u32::<T>()?;
-        self.position = reader.read_u64::<T>()?;
+        self.reserved = read_int::<T>(reader, header)?;
+        self.position = read_size::<T>(reader, header)?;
+        Ok(())
+    }
+}
+
+impl HeaderWritable for HSStructSlot {
+    fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+    ) -> Result<(), HkscError> {
+        write_string::<T>(writer, header, &self.name)?;
+        write_size::<T>(writer, header, self.struct_id)?;
+        write_int::<T>(writer, header, u8::from(self.type_).into())?;
+        write_int::<T>(writer, header, self.reserved)?;
+        write_size::<T>(writer, header, self.position)?;
         Ok(())
     }
 }
@@ -51,6 +73,7 @@ impl Display for HSStructSlot {
 }
 
 #[derive(Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 /// A block containing structure information including slots and inheritance
 pub struct HSStructPrototype {
     /// Name of the structure
@@ -78,24 +101,55 @@ impl HSStructPrototype {
         header: &HSHeader,
         enable_inheritance: bool,
     ) -> Result<(), HkscError> {
-        self.id = reader.read_u64::<T>()?;
-        self.has_meta = reader.read_u32::<T>()? != 0;
-        self.has_proxy = reader.read_u32::<T>()? != 0;
-        self.slot_count = reader.read_u64::<T>()?;
+        self.id = read_size::<T>(reader, header)?;
+        self.has_meta = read_int::<T>(reader, header)? != 0;
+        self.has_proxy = read_int::<T>(reader, header)? != 0;
+        self.slot_count = read_size::<T>(reader, header)?;
         if enable_inheritance {
-            self.inherited_count = reader.read_u32::<T>()?;
+            self.inherited_count = read_int::<T>(reader, header)?;
             self.inherited_structs = (0..self.inherited_count)
                 .map(|_| read_string::<T>(reader, header))
                 .collect::<Result<Vec<_>, HkscError>>()?;
         }
-        self.slots = reader.read_header_enumerable::<HSStructSlot, T>(self.slot_count, header)?;
+        self.slots =
+            reader.read_header_enumerable::<HSStructSlot, T>(self.slot_count, header, "slots")?;
+        Ok(())
+    }
+
+    pub fn write<T: ByteOrder>(
+        &self,
+        writer: &mut impl WriterExt,
+        header: &HSHeader,
+        enable_inheritance: bool,
+    ) -> Result<(), HkscError> {
+        write_size::<T>(writer, header, self.id)?;
+        write_int::<T>(writer, header, self.has_meta.into())?;
+        write_int::<T>(writer, header, self.has_proxy.into())?;
+        write_size::<T>(writer, header, u64::try_from(self.slots.len())?)?;
+        if enable_inheritance {
+            write_int::<T>(writer, header, u32::try_from(self.inherited_structs.len())?)?;
+            for name in &self.inherited_structs {
+                write_string::<T>(writer, header, name)?;
+            }
+        }
+        writer.write_header_enumerable::<HSStructSlot, T>(&self.slots, header)?;
         Ok(())
     }
 }
 
+impl HSStructPrototype {
+    /// Returns the slot at `position` among the structure's own slots, which is how slot
+    /// instructions refer to slots. Use `HSInheritance::slot_at` to include inherited slots.
+    #[must_use]
+    pub fn slot_at(&self, position: i32) -> Option<&HSStructSlot> {
+        let position = u64::try_from(position).ok()?;
+        self.slots.iter().find(|slot| slot.position == position)
+    }
+}
+
 impl Display for HSStructPrototype {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}{}", "[Structure: ".green(), self.name.bright_cyan(),)?;
+        write!(f, "{}{}", "[Structure: ".green(), self.name.bright_cyan())?;
         if !self.inherited_structs.is_empty() {
             write!(
                 f,
@@ -108,3 +162,41 @@ impl Display for HSStructPrototype {
         Ok(())
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+/// Type that a type code of a `CheckType` instruction refers to.
+pub enum HSTypeCode<'a> {
+    /// One of the built-in types.
+    Type(HSType),
+    /// A structure, by the id of its prototype.
+    Struct(&'a HSStructPrototype),
+    /// Neither a built-in type nor the id of a known structure prototype.
+    Unknown(i32),
+}
+
+impl<'a> HSTypeCode<'a> {
+    /// Resolves a type code, codes of built-in types taking precedence over structure ids.
+    #[must_use]
+    pub fn resolve(code: i32, structs: &'a [HSStructPrototype]) -> Self {
+        if let Some(type_) = u8::try_from(code)
+            .ok()
+            .and_then(|code| HSType::try_from(code).ok())
+        {
+            return Self::Type(type_);
+        }
+        u64::try_from(code)
+            .ok()
+            .and_then(|id| structs.iter().find(|structure| structure.id == id))
+            .map_or(Self::Unknown(code), Self::Struct)
+    }
+}
+
+impl Display for HSTypeCode<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Type(type_) => write!(f, "{type_:?}"),
+            Self::Struct(structure) => write!(f, "struct {}", structure.name),
+            Self::Unknown(code) => write!(f, "unknown type {code}"),
+        }
+    }
+}
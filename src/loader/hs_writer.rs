@@ -0,0 +1,152 @@
+use super::hs_header::HSHeader;
+use crate::{common::errors::HkscError, common::extensions::WriterExt};
+
+use byteorder::{ByteOrder, WriteBytesExt};
+
+/// Writes a number to the provided writer based on the number size specified in the header.
+///
+/// # Arguments
+///
+/// * `writer` - A mutable reference to an object that implements the `WriteBytesExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the number size.
+/// * `value` - The number to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the number is successfully written.
+/// * `Err(HkscError::InvalidNumberSize)` - If the number size specified in the header is invalid.
+pub fn write_number<T: ByteOrder>(
+    writer: &mut impl WriteBytesExt,
+    header: &HSHeader,
+    value: f64,
+) -> Result<(), HkscError> {
+    match header.number_size {
+        // Numbers of 4 byte files were read from an `f32`, so this is lossless for them.
+        #[allow(clippy::cast_possible_truncation)]
+        4 => writer.write_f32::<T>(value as f32)?,
+        8 => writer.write_f64::<T>(value)?,
+        _ => return Err(HkscError::InvalidNumberSize(header.number_size)),
+    }
+    Ok(())
+}
+
+/// Writes an integer to the provided writer based on the integer size specified in the header.
+///
+/// # Arguments
+///
+/// * `writer` - A mutable reference to an object that implements the `WriteBytesExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the integer size.
+/// * `value` - The integer to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the integer is successfully written.
+/// * `Err(HkscError::InvalidIntSize)` - If the integer size specified in the header is invalid.
+pub fn write_int<T: ByteOrder>(
+    writer: &mut impl WriteBytesExt,
+    header: &HSHeader,
+    value: u32,
+) -> Result<(), HkscError> {
+    match header.int_size {
+        4 => writer.write_u32::<T>(value)?,
+        8 => writer.write_u64::<T>(value.into())?,
+        _ => return Err(HkscError::InvalidIntSize(header.int_size)),
+    }
+    Ok(())
+}
+
+/// Writes a size to the provided writer based on the type size specified in the header.
+///
+/// # Arguments
+///
+/// * `writer` - A mutable reference to an object that implements the `WriteBytesExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the type size.
+/// * `value` - The size to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the size is successfully written.
+/// * `Err(HkscError::InvalidTypeSize)` - If the type size specified in the header is invalid.
+pub fn write_size<T: ByteOrder>(
+    writer: &mut impl WriteBytesExt,
+    header: &HSHeader,
+    value: u64,
+) -> Result<(), HkscError> {
+    match header.t_size {
+        4 => writer.write_u32::<T>(u32::try_from(value)?)?,
+        8 => writer.write_u64::<T>(value)?,
+        _ => return Err(HkscError::InvalidTypeSize(header.t_size)),
+    }
+    Ok(())
+}
+
+/// Writes a string size to the provided writer based on the string size specified in the header.
+///
+/// # Arguments
+///
+/// * `writer` - A mutable reference to an object that implements the `WriteBytesExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the string size.
+/// * `size` - The size to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the size is successfully written.
+/// * `Err(HkscError::InvalidStringSize)` - If the string size specified in the header is invalid.
+fn write_string_size<T: ByteOrder>(
+    writer: &mut impl WriteBytesExt,
+    header: &HSHeader,
+    size: usize,
+) -> Result<(), HkscError> {
+    match header.t_size {
+        4 => writer.write_u32::<T>(u32::try_from(size)?)?,
+        8 => writer.write_u64::<T>(u64::try_from(size)?)?,
+        _ => return Err(HkscError::InvalidStringSize(header.t_size)),
+    }
+    Ok(())
+}
+
+/// Writes a null terminated string to the provided writer based on the string size specified in the header.
+/// Empty strings are written with a size of zero, which is how absent names are stored.
+///
+/// # Arguments
+///
+/// * `writer` - A mutable reference to an object that implements the `WriterExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the string size.
+/// * `value` - The string to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the string is successfully written.
+/// * `Err(HkscError::InvalidStringSize)` - If the string size specified in the header is invalid.
+pub fn write_string<T: ByteOrder>(
+    writer: &mut impl WriterExt,
+    header: &HSHeader,
+    value: &str,
+) -> Result<(), HkscError> {
+    if value.is_empty() {
+        return write_string_size::<T>(writer, header, 0);
+    }
+    write_terminated_string::<T>(writer, header, value)
+}
+
+/// Writes a null terminated string to the provided writer based on the string size specified in the header.
+/// Unlike `write_string`, empty strings still get their null terminator, as string constants always have one.
+///
+/// # Arguments
+///
+/// * `writer` - A mutable reference to an object that implements the `WriterExt` trait.
+/// * `header` - A reference to the `HSHeader` containing metadata about the string size.
+/// * `value` - The string to write.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the string is successfully written.
+/// * `Err(HkscError::InvalidStringSize)` - If the string size specified in the header is invalid.
+pub fn write_terminated_string<T: ByteOrder>(
+    writer: &mut impl WriterExt,
+    header: &HSHeader,
+    value: &str,
+) -> Result<(), HkscError> {
+    write_string_size::<T>(writer, header, value.len() + 1)?;
+    writer.write_fixed_string::<T>(value, value.len() + 1)
+}
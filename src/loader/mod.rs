@@ -3,10 +3,17 @@
 pub mod hs;
 pub mod hs_constant;
 pub mod hs_debug;
+pub mod hs_diagnostics;
+pub mod hs_encoding;
 pub mod hs_enums;
 pub mod hs_function;
 pub mod hs_header;
+pub mod hs_inheritance;
 pub mod hs_instruction;
 pub mod hs_opcodes;
+pub mod hs_options;
+pub mod hs_profile;
 pub mod hs_reader;
+pub mod hs_source;
 pub mod hs_structure;
+pub mod hs_writer;
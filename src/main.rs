@@ -1,55 +1,258 @@
 #![deny(clippy::pedantic)]
 #![allow(clippy::missing_errors_doc)]
 
-pub mod common;
-pub mod loader;
-
-use crate::common::errors::HkscError;
-use clap::Parser;
-use loader::hs::HavokScriptFile;
-use std::{
-    fs::File,
-    io::{BufReader, Write},
-    path::PathBuf,
+use clap::{Parser, ValueEnum};
+use hkscdis_rs::{
+    assembler::{self, listing::HSListing},
+    cfg::dot::HSDotGraph,
+    common::errors::HkscError,
+    decompiler::HSDecompiler,
+    export::{emmylua::HSLuaStubs, header::HSCHeader},
+    loader::{
+        hs::HavokScriptFile,
+        hs_function::HSDisplayContext,
+        hs_options::HSReadOptions,
+        hs_profile::{HSOpcodeProfile, PRESETS},
+        hs_source::HSSources,
+    },
+    verifier::HSVerifyReport,
 };
+use std::{fs::File, io::Write, path::PathBuf, process::ExitCode};
+
+#[derive(Clone, Copy, ValueEnum)]
+/// Output formats for the disassembly.
+enum OutputFormat {
+    /// Human-readable disassembly.
+    Text,
+    /// Editable listing that can be assembled back with `--assemble`.
+    Listing,
+    /// Decompiled Lua source.
+    Lua,
+    /// Graphviz graph of each function's control flow.
+    Dot,
+    /// `EmmyLua` `---@class` stubs of the structure prototypes.
+    LuaStubs,
+    /// C header with the ids and slot offsets of the structure prototypes.
+    CHeader,
+    /// JSON document of the whole parsed file.
+    #[cfg(feature = "serde")]
+    Json,
+}
 
 #[derive(Parser)]
 #[command(name = "Havok Script Disassembler")]
+#[allow(clippy::struct_excessive_bools)]
 /// A CLI tool to disassemble Havok Script 5.1 files
 struct Disassembler {
     #[arg(short, long, value_name = "FILE")]
     /// File to disassemble.
     path: PathBuf,
-    #[arg(short = 'i', long)]
-    /// Enable extensions for structure inheritance.
-    enable_inheritance: bool,
+    #[arg(short = 'i', long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+    /// Override the detected structure inheritance extension, `-i` enabling it and `-i false` disabling it.
+    enable_inheritance: Option<bool>,
+    #[arg(short = 'l', long)]
+    /// Keep instructions with unknown opcodes as `Unknown` instead of failing.
+    lenient: bool,
+    #[arg(long, value_name = "PRESET|FILE")]
+    /// Opcode profile to decode and encode instructions with, either a built-in preset or a TOML/JSON file.
+    profile: Option<String>,
+    #[arg(short = 'r', long)]
+    /// Output everything read before a failure instead of failing, reporting the failure as a warning.
+    recover: bool,
     #[arg(short = 'c', long, default_value = "false")]
     /// Disable displaying colors with the disassembly.
     disable_colors: bool,
     #[arg(short = 'o', long, value_name = "FILE")]
     /// Optional output file. If not specified, output goes to stdout.
     output: Option<PathBuf>,
+    #[arg(short = 'f', long, value_enum, default_value_t = OutputFormat::Text)]
+    /// Format of the disassembly.
+    format: OutputFormat,
+    #[arg(long)]
+    /// Nest child functions as clusters of their parent's graph with `--format dot`.
+    clusters: bool,
+    #[arg(short = 'F', long, value_name = "ID")]
+    /// Only output the function with this id, such as `0/3/1`.
+    function: Option<String>,
+    #[arg(short = 's', long, value_name = "DIR")]
+    /// Directory of the original Lua sources, whose lines are shown above the instructions they compiled to.
+    source: Option<PathBuf>,
+    #[arg(long)]
+    /// Check operands, jumps, returns and debug lines instead of disassembling, failing on any violation.
+    verify: bool,
+    #[arg(short = 'a', long)]
+    /// Assemble the listing at `path` into bytecode instead of disassembling.
+    assemble: bool,
+}
+
+/// Resolves the `--profile` argument, which is either the name of a preset or a profile file.
+fn load_profile(profile: &str) -> Result<HSOpcodeProfile, HkscError> {
+    if let Some(preset) = HSOpcodeProfile::preset(profile) {
+        return Ok(preset);
+    }
+    #[cfg(feature = "serde")]
+    if std::path::Path::new(profile).is_file() {
+        return HSOpcodeProfile::load(std::path::Path::new(profile));
+    }
+    Err(HkscError::InvalidOpcodeProfile(format!(
+        "`{profile}` is neither a preset ({}) nor a profile file",
+        PRESETS.join(", ")
+    )))
+}
+
+fn main() -> ExitCode {
+    match run(Disassembler::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
-fn main() -> Result<(), HkscError> {
-    let cli = Disassembler::parse();
-    let file = File::open(cli.path)?;
-    let mut reader = BufReader::new(file);
-    let mut havok_script_file = HavokScriptFile::default();
+fn run(cli: Disassembler) -> Result<(), HkscError> {
+    if cli.assemble {
+        let source = std::fs::read_to_string(cli.path)?;
+        let bytes = match &cli.profile {
+            Some(profile) => assembler::assemble_with_profile(&source, &load_profile(profile)?)?,
+            None => assembler::assemble(&source)?,
+        };
+        match cli.output {
+            Some(path) => std::fs::write(path, bytes)?,
+            None => std::io::stdout().write_all(&bytes)?,
+        }
+        return Ok(());
+    }
+
+    let mut file = File::open(&cli.path)?;
 
-    if cli.disable_colors {
+    // ANSI escape codes don't work in files
+    if cli.disable_colors || cli.output.is_some() {
         colored::control::set_override(false);
     }
 
-    havok_script_file.read(&mut reader, cli.enable_inheritance)?;
+    let options = HSReadOptions {
+        enable_inheritance: cli.enable_inheritance,
+        lenient: cli.lenient,
+        profile: match &cli.profile {
+            Some(profile) => load_profile(profile)?,
+            None => HSOpcodeProfile::default(),
+        },
+        recover: cli.recover,
+        encoding: None,
+    };
+    let havok_script_file = HavokScriptFile::from_reader_with_options(&mut file, &options)?;
+    for diagnostic in &havok_script_file.diagnostics {
+        eprintln!("Warning: {diagnostic}");
+    }
+    for issue in &havok_script_file.inheritance.issues {
+        eprintln!("Warning: {issue}");
+    }
+    if cli.verify {
+        return verify(&havok_script_file, &cli);
+    }
+    let sources = cli
+        .source
+        .as_ref()
+        .map(|root| HSSources::load(root, &havok_script_file.main_function));
+    if let Some(sources) = &sources {
+        for chunk_name in sources.missing() {
+            eprintln!("Warning: Source of {chunk_name} not found!");
+        }
+    }
+    let disassembly = match &cli.function {
+        Some(id) => function_disassembly(&havok_script_file, id, sources.as_ref(), &cli)?,
+        None => file_disassembly(&havok_script_file, sources.as_ref(), &cli)?,
+    };
 
     match cli.output {
         Some(path) => {
-            colored::control::set_override(false); // ANSI escape codes don't work in files
             let mut output_file = File::create(path)?;
-            write!(output_file, "{havok_script_file}")?;
+            write!(output_file, "{disassembly}")?;
         }
-        None => println!("{havok_script_file}"),
+        None => println!("{disassembly}"),
     }
     Ok(())
 }
+
+/// Disassembles the whole file in the selected format.
+#[cfg_attr(not(feature = "serde"), allow(clippy::unnecessary_wraps))]
+fn file_disassembly(
+    havok_script_file: &HavokScriptFile,
+    sources: Option<&HSSources>,
+    cli: &Disassembler,
+) -> Result<String, HkscError> {
+    Ok(match cli.format {
+        OutputFormat::Text => match sources {
+            Some(sources) => havok_script_file.display_with_sources(sources).to_string(),
+            None => havok_script_file.to_string(),
+        },
+        OutputFormat::Listing => HSListing::new(havok_script_file).to_string(),
+        OutputFormat::Lua => HSDecompiler::new(havok_script_file).to_string(),
+        OutputFormat::Dot => HSDotGraph::new(havok_script_file, cli.clusters).to_string(),
+        OutputFormat::LuaStubs => HSLuaStubs::new(havok_script_file).to_string(),
+        OutputFormat::CHeader => HSCHeader::new(havok_script_file).to_string(),
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => serde_json::to_string_pretty(havok_script_file)?,
+    })
+}
+
+/// Disassembles the function with the given id in the selected format, without its children
+/// for the text format.
+fn function_disassembly(
+    havok_script_file: &HavokScriptFile,
+    id: &str,
+    sources: Option<&HSSources>,
+    cli: &Disassembler,
+) -> Result<String, HkscError> {
+    let function = havok_script_file
+        .main_function
+        .find(id)
+        .ok_or_else(|| HkscError::FunctionNotFound(id.to_string()))?;
+    Ok(match cli.format {
+        OutputFormat::Text => function
+            .display_single(HSDisplayContext {
+                sources,
+                structs: &havok_script_file.structs,
+                inheritance: Some(&havok_script_file.inheritance),
+            })
+            .to_string(),
+        OutputFormat::Listing => return Err(HkscError::PartialListing),
+        OutputFormat::Lua => {
+            HSDecompiler::for_function(function, &havok_script_file.structs).to_string()
+        }
+        OutputFormat::Dot => HSDotGraph::for_function(function, cli.clusters).to_string(),
+        // Structures belong to the whole file rather than to a function.
+        OutputFormat::LuaStubs | OutputFormat::CHeader => {
+            return file_disassembly(havok_script_file, sources, cli);
+        }
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => serde_json::to_string_pretty(function)?,
+    })
+}
+
+/// Writes the verification report of a file, or of the function selected with `--function`,
+/// failing if it has violations or the file wasn't fully read.
+fn verify(file: &HavokScriptFile, cli: &Disassembler) -> Result<(), HkscError> {
+    let report = match &cli.function {
+        Some(id) => HSVerifyReport::for_id(file, id)
+            .ok_or_else(|| HkscError::FunctionNotFound(id.clone()))?,
+        None => HSVerifyReport::new(file),
+    };
+    let output = match cli.format {
+        #[cfg(feature = "serde")]
+        OutputFormat::Json => serde_json::to_string_pretty(&report)?,
+        _ => report.to_string(),
+    };
+    match &cli.output {
+        Some(path) => std::fs::write(path, output)?,
+        None => print!("{output}"),
+    }
+    if !report.violations.is_empty() {
+        Err(HkscError::VerificationFailed(report.violations.len()))
+    } else if report.incomplete {
+        Err(HkscError::IncompleteVerification)
+    } else {
+        Ok(())
+    }
+}
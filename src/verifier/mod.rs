@@ -0,0 +1,318 @@
+//! Structural checks of `HavokScript` bytecode.
+//!
+//! Reading a file only ensures that it can be parsed. The verifier additionally checks that every
+//! function is consistent with itself: operands refer to existing registers, constants, child
+//! functions and upvalues, jumps land inside the function, type checks refer to known types, the
+//! function ends with a `Return`, and its debug info has a line for every instruction when it has
+//! lines at all. Patched or hand-assembled scripts that pass these checks won't make the
+//! interpreter read out of bounds.
+
+use crate::loader::{
+    hs::HavokScriptFile,
+    hs_function::HSFunction,
+    hs_instruction::HSInstruction,
+    hs_opcodes::{HSOpArgMode, HSOpCode},
+    hs_structure::{HSStructPrototype, HSTypeCode},
+};
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(tag = "type"))]
+/// Inconsistency found in a function.
+pub enum HSViolationKind {
+    /// A register operand is not below the function's slot count.
+    RegisterOutOfRange { register: i32, slot_count: u32 },
+    /// A constant operand is not below the number of constants.
+    ConstantOutOfRange {
+        constant: i32,
+        constant_count: usize,
+    },
+    /// A `Closure` refers to a child function that doesn't exist.
+    ChildOutOfRange { child: i32, child_count: usize },
+    /// A `GetUpval` or `SetUpval` refers to an upvalue that doesn't exist.
+    UpvalueOutOfRange { up_value: i32, up_value_count: u32 },
+    /// A jump lands outside of the function.
+    JumpOutOfRange {
+        target: i64,
+        instruction_count: usize,
+    },
+    /// A `CheckType` refers to neither a built-in type nor a known structure prototype.
+    UnknownTypeCode { code: i32 },
+    /// An instruction's opcode isn't known, so its operands can't be checked.
+    UnknownOpcode { raw: u64 },
+    /// The last instruction isn't a `Return`, so execution can run past the end of the function.
+    MissingReturn,
+    /// The debug info has line numbers, but not exactly one per instruction.
+    LineCountMismatch {
+        line_count: usize,
+        instruction_count: u64,
+    },
+}
+
+impl Display for HSViolationKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RegisterOutOfRange {
+                register,
+                slot_count,
+            } => write!(
+                f,
+                "register {register} is out of range of {slot_count} slots"
+            ),
+            Self::ConstantOutOfRange {
+                constant,
+                constant_count,
+            } => write!(
+                f,
+                "constant {constant} is out of range of {constant_count} constants"
+            ),
+            Self::ChildOutOfRange { child, child_count } => write!(
+                f,
+                "closure {child} is out of range of {child_count} child functions"
+            ),
+            Self::UpvalueOutOfRange {
+                up_value,
+                up_value_count,
+            } => write!(
+                f,
+                "upvalue {up_value} is out of range of {up_value_count} upvalues"
+            ),
+            Self::JumpOutOfRange {
+                target,
+                instruction_count,
+            } => write!(
+                f,
+                "jump to {target} is out of range of {instruction_count} instructions"
+            ),
+            Self::UnknownTypeCode { code } => write!(f, "type code {code} is unknown"),
+            Self::UnknownOpcode { raw } => write!(f, "unknown instruction {raw:#010x}"),
+            Self::MissingReturn => write!(f, "function doesn't end with a Return"),
+            Self::LineCountMismatch {
+                line_count,
+                instruction_count,
+            } => write!(
+                f,
+                "debug info has {line_count} lines for {instruction_count} instructions"
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Violation found by the verifier, with where it was found.
+pub struct HSViolation {
+    /// Path of the function, such as `main > child[3]`, following the paths of read errors.
+    pub path: Vec<String>,
+    /// Index of the offending instruction, `None` for violations of the function as a whole.
+    pub pc: Option<usize>,
+    /// What is wrong.
+    pub kind: HSViolationKind,
+}
+
+impl Display for HSViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.join(" > "))?;
+        if let Some(pc) = self.pc {
+            write!(f, " [{pc}]")?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+/// Result of verifying a file or a function tree.
+pub struct HSVerifyReport {
+    /// Violations, ordered by function in pre-order and then by instruction.
+    pub violations: Vec<HSViolation>,
+    /// Whether the file wasn't fully read, as listed in `HavokScriptFile::diagnostics`, so the
+    /// parts that were skipped couldn't be verified.
+    pub incomplete: bool,
+}
+
+impl HSVerifyReport {
+    /// Verifies every function of a file.
+    #[must_use]
+    pub fn new(file: &HavokScriptFile) -> Self {
+        let mut report =
+            Self::for_function(&file.main_function, &file.structs, &["main".to_string()]);
+        report.incomplete = !file.diagnostics.is_empty();
+        report
+    }
+
+    /// Verifies the function of a file with the given id, such as `0/3/1`, and all of its
+    /// children. Returns `None` when no function has that id.
+    #[must_use]
+    pub fn for_id(file: &HavokScriptFile, id: &str) -> Option<Self> {
+        let function = file.main_function.find(id)?;
+        // Ids are the indices of the children leading to the function, below the main function.
+        let path = std::iter::once("main".to_string())
+            .chain(id.split('/').skip(1).map(|index| format!("child[{index}]")))
+            .collect::<Vec<_>>();
+        let mut report = Self::for_function(function, &file.structs, &path);
+        report.incomplete = !file.diagnostics.is_empty();
+        Some(report)
+    }
+
+    /// Verifies a function and all of its children, `path` being the path of `function` and
+    /// `structs` the structure prototypes its type checks can refer to.
+    #[must_use]
+    pub fn for_function(
+        function: &HSFunction,
+        structs: &[HSStructPrototype],
+        path: &[String],
+    ) -> Self {
+        let mut report = Self::default();
+        report.verify(function, structs, path);
+        report
+    }
+
+    /// Returns whether no violations were found in a fully read file.
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty() && !self.incomplete
+    }
+
+    fn verify(&mut self, function: &HSFunction, structs: &[HSStructPrototype], path: &[String]) {
+        let mut violations = Vec::new();
+        for (pc, instruction) in function.instructions.iter().enumerate() {
+            violations.extend(
+                Self::verify_instruction(function, instruction, pc, structs)
+                    .into_iter()
+                    .map(|kind| (Some(pc), kind)),
+            );
+        }
+        if function
+            .instructions
+            .last()
+            .is_none_or(|instruction| instruction.mode != HSOpCode::Return)
+        {
+            violations.push((None, HSViolationKind::MissingReturn));
+        }
+        // Stripped debug info keeps names but has no line numbers at all, which is valid.
+        if function.has_debug_info
+            && !function.debug_info.lines.is_empty()
+            && u64::try_from(function.debug_info.lines.len())
+                .is_ok_and(|line_count| line_count != function.instruction_count)
+        {
+            violations.push((
+                None,
+                HSViolationKind::LineCountMismatch {
+                    line_count: function.debug_info.lines.len(),
+                    instruction_count: function.instruction_count,
+                },
+            ));
+        }
+        self.violations
+            .extend(violations.into_iter().map(|(pc, kind)| HSViolation {
+                path: path.to_vec(),
+                pc,
+                kind,
+            }));
+
+        for (index, child) in function.child_functions.iter().enumerate() {
+            let mut child_path = path.to_vec();
+            child_path.push(format!("child[{index}]"));
+            self.verify(child, structs, &child_path);
+        }
+    }
+
+    /// Returns the violations of a single instruction.
+    fn verify_instruction(
+        function: &HSFunction,
+        instruction: &HSInstruction,
+        pc: usize,
+        structs: &[HSStructPrototype],
+    ) -> Vec<HSViolationKind> {
+        if instruction.mode == HSOpCode::Unknown {
+            return vec![HSViolationKind::UnknownOpcode {
+                raw: instruction.raw,
+            }];
+        }
+
+        let mut violations = Vec::new();
+        for arg in &instruction.args {
+            match arg.mode {
+                HSOpArgMode::REG
+                    if u32::try_from(arg.value)
+                        .is_ok_and(|register| register >= function.slot_count) =>
+                {
+                    violations.push(HSViolationKind::RegisterOutOfRange {
+                        register: arg.value,
+                        slot_count: function.slot_count,
+                    });
+                }
+                HSOpArgMode::CONST
+                    if usize::try_from(arg.value)
+                        .is_ok_and(|constant| constant >= function.constants.len()) =>
+                {
+                    violations.push(HSViolationKind::ConstantOutOfRange {
+                        constant: arg.value,
+                        constant_count: function.constants.len(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // B holds the index of the child function or upvalue.
+        let operand = instruction.args.get(1).map_or(0, |arg| arg.value);
+        match instruction.mode {
+            HSOpCode::Closure
+                if usize::try_from(operand)
+                    .is_ok_and(|child| child >= function.child_functions.len()) =>
+            {
+                violations.push(HSViolationKind::ChildOutOfRange {
+                    child: operand,
+                    child_count: function.child_functions.len(),
+                });
+            }
+            HSOpCode::GetUpval | HSOpCode::SetUpval | HSOpCode::SetUpvalR1
+                if u32::try_from(operand)
+                    .is_ok_and(|up_value| up_value >= function.up_value_count) =>
+            {
+                violations.push(HSViolationKind::UpvalueOutOfRange {
+                    up_value: operand,
+                    up_value_count: function.up_value_count,
+                });
+            }
+            HSOpCode::CheckType | HSOpCode::CheckTypes | HSOpCode::CheckTypeD
+                if matches!(
+                    HSTypeCode::resolve(operand, structs),
+                    HSTypeCode::Unknown(_)
+                ) =>
+            {
+                violations.push(HSViolationKind::UnknownTypeCode { code: operand });
+            }
+            HSOpCode::Jmp | HSOpCode::ForPrep | HSOpCode::ForLoop
+                if instruction
+                    .jump_target(pc)
+                    .is_none_or(|target| target >= function.instructions.len()) =>
+            {
+                violations.push(HSViolationKind::JumpOutOfRange {
+                    target: i64::try_from(pc).unwrap_or(i64::MAX) + 1 + i64::from(operand),
+                    instruction_count: function.instructions.len(),
+                });
+            }
+            _ => {}
+        }
+        violations
+    }
+}
+
+impl Display for HSVerifyReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for violation in &self.violations {
+            writeln!(f, "{violation}")?;
+        }
+        if self.incomplete {
+            writeln!(
+                f,
+                "file was not fully read, so it was only partially verified"
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::{HEADER, read};
+use hkscdis_rs::{
+    assembler::{assemble, listing::HSListing},
+    loader::hs_opcodes::{HSOpArgMode, HSOpCode},
+};
+
+/// Returns a listing of a main function running `instruction` with two number constants.
+fn single_instruction(instruction: &str) -> String {
+    format!(
+        "{HEADER}[Function]
+- Slot Count: 3
+Instructions:
+- {instruction}
+- Return: REG(0) NUMBER(1)
+Constants:
+- TNUMBER: 1.0
+- TNUMBER: 2.0
+[End Function]
+"
+    )
+}
+
+#[test]
+fn assembles_listing_back_into_the_same_file() {
+    let listing = include_str!("fixtures/program.lst");
+    let bytes = assemble(listing).unwrap();
+    assert_eq!(bytes, include_bytes!("fixtures/program.luac"));
+    assert_eq!(HSListing::new(&read(&bytes)).to_string(), listing);
+}
+
+#[test]
+fn assembles_padded_enums_back_into_the_same_file() {
+    let bytes = include_bytes!("fixtures/padded_enums.luac");
+    let file = read(bytes);
+    assert_eq!(file.enums[0].name, "TNIL");
+    assert_eq!(file.enums[0].length, 8);
+    let listing = HSListing::new(&file).to_string();
+    assert!(listing.contains("- TNIL: 0 8\n"));
+    assert_eq!(assemble(&listing).unwrap(), bytes);
+}
+
+#[test]
+fn assembles_constant_b_of_bk_variants() {
+    let bytes = assemble(&single_instruction("AddBk: REG(0) CONST(1) REG(2)")).unwrap();
+    let instruction = &read(&bytes).main_function.instructions[0];
+    assert_eq!(instruction.mode, HSOpCode::AddBk);
+    let args = instruction
+        .args
+        .iter()
+        .map(|arg| (arg.mode.clone(), arg.value))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        args,
+        [
+            (HSOpArgMode::REG, 0),
+            (HSOpArgMode::CONST, 1),
+            (HSOpArgMode::REG, 2),
+        ]
+    );
+}
+
+#[test]
+fn rejects_constant_b_of_other_variants() {
+    let error = assemble(&single_instruction("Add: REG(0) CONST(1) REG(2)")).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("unexpected CONST operand for Add")
+    );
+    assemble(&single_instruction("Add: REG(0) REG(1) CONST(1)")).unwrap();
+}
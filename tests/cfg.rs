@@ -0,0 +1,196 @@
+mod common;
+
+use common::instruction;
+use hkscdis_rs::{
+    cfg::{HSControlFlowGraph, HSEdgeKind, dot::HSDotGraph},
+    loader::{
+        hs_function::HSFunction,
+        hs_instruction::HSInstruction,
+        hs_opcodes::{HSOpArgMode, HSOpCode},
+    },
+};
+
+fn graph(instructions: Vec<HSInstruction>) -> HSControlFlowGraph {
+    HSControlFlowGraph::new(&HSFunction {
+        instructions,
+        ..HSFunction::default()
+    })
+}
+
+fn jump(mode: HSOpCode, register: i32, offset: i32) -> HSInstruction {
+    instruction(
+        mode,
+        &[(HSOpArgMode::REG, register), (HSOpArgMode::NUMBER, offset)],
+    )
+}
+
+fn load(register: i32) -> HSInstruction {
+    instruction(
+        HSOpCode::LoadK,
+        &[(HSOpArgMode::REG, register), (HSOpArgMode::CONST, 0)],
+    )
+}
+
+fn ret() -> HSInstruction {
+    instruction(
+        HSOpCode::Return,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 1)],
+    )
+}
+
+/// Returns the first instruction of each block.
+fn starts(graph: &HSControlFlowGraph) -> Vec<usize> {
+    graph.blocks.iter().map(|block| block.start).collect()
+}
+
+/// Returns the target blocks and kinds of the edges leaving `block`.
+fn successors(graph: &HSControlFlowGraph, block: usize) -> Vec<(usize, HSEdgeKind)> {
+    graph
+        .successors(block)
+        .map(|edge| (edge.to, edge.kind))
+        .collect()
+}
+
+#[test]
+fn joins_both_branches_of_a_diamond() {
+    // if r0 then r1 = k0 else r1 = k0 end
+    let graph = graph(vec![
+        instruction(
+            HSOpCode::Test,
+            &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 0)],
+        ),
+        jump(HSOpCode::Jmp, 0, 2),
+        load(1),
+        jump(HSOpCode::Jmp, 0, 1),
+        load(1),
+        ret(),
+    ]);
+    assert_eq!(starts(&graph), [0, 1, 2, 4, 5]);
+    assert_eq!(
+        successors(&graph, 0),
+        [
+            (1, HSEdgeKind::Fallthrough),
+            (2, HSEdgeKind::ConditionalSkip)
+        ]
+    );
+    assert_eq!(successors(&graph, 1), [(3, HSEdgeKind::Jump)]);
+    assert_eq!(successors(&graph, 2), [(4, HSEdgeKind::Jump)]);
+    assert_eq!(successors(&graph, 3), [(4, HSEdgeKind::Fallthrough)]);
+    assert_eq!(
+        graph.immediate_dominators,
+        [None, Some(0), Some(0), Some(1), Some(0)]
+    );
+    assert_eq!(graph.predecessors(4).count(), 2);
+    assert!(graph.dominates(0, 4));
+    assert!(!graph.dominates(2, 4));
+    assert!(graph.loops.is_empty());
+}
+
+#[test]
+fn finds_nested_loops() {
+    // for r0 = ... do for r4 = ... do r8 = k0 end r9 = k0 end
+    let graph = graph(vec![
+        jump(HSOpCode::ForPrep, 0, 4),
+        jump(HSOpCode::ForPrep, 4, 1),
+        load(8),
+        jump(HSOpCode::ForLoop, 4, -2),
+        load(9),
+        jump(HSOpCode::ForLoop, 0, -5),
+        ret(),
+    ]);
+    assert_eq!(starts(&graph), [0, 1, 2, 3, 4, 5, 6]);
+    assert_eq!(
+        successors(&graph, 3),
+        [(4, HSEdgeKind::Fallthrough), (2, HSEdgeKind::Jump)]
+    );
+    assert_eq!(successors(&graph, 2), [(3, HSEdgeKind::LoopBack)]);
+    assert_eq!(successors(&graph, 4), [(5, HSEdgeKind::LoopBack)]);
+
+    let headers = graph.loops.iter().map(|lp| lp.header).collect::<Vec<_>>();
+    assert_eq!(headers, [3, 5]);
+    let inner = &graph.loops[0];
+    assert_eq!(inner.latches, [2]);
+    assert_eq!(inner.body.iter().copied().collect::<Vec<_>>(), [2, 3]);
+    let outer = &graph.loops[1];
+    assert_eq!(outer.latches, [4]);
+    assert_eq!(
+        outer.body.iter().copied().collect::<Vec<_>>(),
+        [1, 2, 3, 4, 5]
+    );
+    assert_eq!(graph.innermost_loop(2).unwrap().header, 3);
+    assert_eq!(graph.innermost_loop(4).unwrap().header, 5);
+    assert!(graph.innermost_loop(6).is_none());
+}
+
+#[test]
+fn leaves_unreachable_blocks_without_dominators() {
+    let graph = graph(vec![jump(HSOpCode::Jmp, 0, 1), load(1), ret()]);
+    assert_eq!(starts(&graph), [0, 1, 2]);
+    assert_eq!(successors(&graph, 0), [(2, HSEdgeKind::Jump)]);
+    assert!(!graph.is_reachable(1));
+    assert!(graph.is_reachable(2));
+    assert_eq!(graph.immediate_dominators, [None, None, Some(0)]);
+    assert!(!graph.dominates(0, 1));
+    assert!(!graph.dominates(1, 1));
+}
+
+#[test]
+fn drops_jumps_past_the_end() {
+    let graph = graph(vec![jump(HSOpCode::Jmp, 0, 5), ret()]);
+    assert_eq!(starts(&graph), [0, 1]);
+    assert!(graph.edges.is_empty());
+    assert!(!graph.is_reachable(1));
+}
+
+/// Returns the labels of the edges leaving the first block of the DOT graph of a comparison or
+/// test, in the order fallthrough, skip.
+fn condition_labels(condition: HSInstruction) -> Vec<String> {
+    let function = HSFunction {
+        instructions: vec![condition, load(1), ret()],
+        ..HSFunction::default()
+    };
+    HSDotGraph::for_function(&function, false)
+        .to_string()
+        .lines()
+        .filter(|line| line.contains("\"f0_b0\" -> "))
+        .map(|line| line.split('"').nth(5).unwrap_or_default().to_string())
+        .collect()
+}
+
+#[test]
+fn labels_conditions_by_their_expected_result() {
+    let lt = |expected| {
+        instruction(
+            HSOpCode::Lt,
+            &[
+                (HSOpArgMode::NUMBER, expected),
+                (HSOpArgMode::REG, 0),
+                (HSOpArgMode::CONST, 0),
+            ],
+        )
+    };
+    assert_eq!(condition_labels(lt(0)), ["false", "true"]);
+    assert_eq!(condition_labels(lt(1)), ["true", "false"]);
+
+    let test = |expected| {
+        instruction(
+            HSOpCode::Test,
+            &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, expected)],
+        )
+    };
+    assert_eq!(condition_labels(test(0)), ["false", "true"]);
+    assert_eq!(condition_labels(test(1)), ["true", "false"]);
+
+    let test_set = |expected| {
+        instruction(
+            HSOpCode::TestSet,
+            &[
+                (HSOpArgMode::REG, 1),
+                (HSOpArgMode::REG, 0),
+                (HSOpArgMode::NUMBER, expected),
+            ],
+        )
+    };
+    assert_eq!(condition_labels(test_set(0)), ["false", "true"]);
+    assert_eq!(condition_labels(test_set(1)), ["true", "false"]);
+}
@@ -0,0 +1,43 @@
+#![allow(dead_code)]
+
+use hkscdis_rs::loader::{
+    hs::HavokScriptFile,
+    hs_instruction::{HSInstruction, HSInstructionArg},
+    hs_opcodes::{HSOpArgMode, HSOpCode},
+    hs_options::HSReadOptions,
+};
+
+/// Header and enums of a big-endian listing with 4 byte integers and no extensions.
+pub const HEADER: &str = "[Header]
+- Is Little Endian: false
+- Integer Size: 4
+- Type Size: 8
+- Instruction Size: 4
+- Number Size: 8
+- Is Using Integer: false
+- Extensions: []
+- Shared: 0
+- Structure Inheritance: false
+
+[Enums]
+";
+
+pub fn read(bytes: &[u8]) -> HavokScriptFile {
+    HavokScriptFile::from_bytes_with_options(bytes, &HSReadOptions::default()).unwrap()
+}
+
+/// Assembles `HEADER` followed by `functions` and reads the result back.
+pub fn assemble(functions: &str) -> HavokScriptFile {
+    read(&hkscdis_rs::assembler::assemble(&format!("{HEADER}{functions}")).unwrap())
+}
+
+pub fn instruction(mode: HSOpCode, args: &[(HSOpArgMode, i32)]) -> HSInstruction {
+    let args = args
+        .iter()
+        .map(|(mode, value)| HSInstructionArg {
+            mode: mode.clone(),
+            value: *value,
+        })
+        .collect();
+    HSInstruction::new(mode, args)
+}
@@ -0,0 +1,426 @@
+mod common;
+
+use common::{assemble, instruction, read};
+use hkscdis_rs::{
+    decompiler::HSDecompiler,
+    loader::hs_opcodes::{HSOpArgMode, HSOpCode},
+};
+
+fn decompile(functions: &str) -> String {
+    HSDecompiler::new(&assemble(functions)).to_string()
+}
+
+/// Returns the debug info section of a listing naming the given locals, with the range of
+/// instructions each is active in, and upvalues.
+fn debug_info(locals: &[(&str, u32, u32)], up_values: &[&str]) -> String {
+    let mut section = "Debug Info:
+- Line Begin: 0
+- Line End: 0
+- Path: \"@test.lua\"
+- Function Name: \"\"
+- Lines:
+- Locals:
+"
+    .to_string();
+    for (name, start, end) in locals {
+        section.push_str(&format!("   - \"{name}\": {start} {end}\n"));
+    }
+    section.push_str("- UpValues:\n");
+    for name in up_values {
+        section.push_str(&format!("   - \"{name}\"\n"));
+    }
+    section
+}
+
+#[test]
+fn decompiles_programs() {
+    let file = read(include_bytes!("fixtures/program.luac"));
+    assert_eq!(
+        HSDecompiler::new(&file).to_string(),
+        "local t = { x = 3, 1, 2 }
+for i = 1, 10 do
+    if 5 < i and t.x then
+        print(i)
+    else
+        print(\"no\")
+    end
+end
+for k, v in pairs(t) do
+    print(k, v)
+end
+local function add(a, b)
+    return a + b
+end
+while t.x < 10 do
+    t.x = t.x + 1
+end
+print(add(1, 2))
+"
+    );
+}
+
+#[test]
+fn decompiles_closures() {
+    let file = read(include_bytes!("fixtures/closures.luac"));
+    assert_eq!(
+        HSDecompiler::new(&file).to_string(),
+        "local function f(obj, ...)
+    local a, b = ...
+    repeat
+        a = a + 1
+        if 100 < a then
+            break
+        end
+    until b <= a
+    if a == 1 or b == 2 then
+        obj:go(a)
+    elseif a then
+        return obj.x
+    end
+    local c = a < b
+    local d = a or b
+    return c, d
+end
+local count = 0
+function inc()
+    count = count + 1
+    return count
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_if_else() {
+    let listing = format!(
+        "[Function]
+- Parameter Count: 1
+- Slot Count: 3
+Instructions:
+- Test: REG(0) NUMBER(0)
+- Jmp: NUMBER(0) NUMBER(4)
+- GetGlobal: REG(1) CONST(0)
+- LoadK: REG(2) CONST(1)
+- Call: REG(1) NUMBER(2) NUMBER(1)
+- Jmp: NUMBER(0) NUMBER(3)
+- GetGlobal: REG(1) CONST(0)
+- LoadK: REG(2) CONST(2)
+- Call: REG(1) NUMBER(2) NUMBER(1)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"print\"
+- TSTRING: \"yes\"
+- TSTRING: \"no\"
+{}[End Function]
+",
+        debug_info(&[("a", 0, 10)], &[])
+    );
+    assert_eq!(
+        decompile(&listing),
+        "if a then
+    print(\"yes\")
+else
+    print(\"no\")
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_numeric_for_loops() {
+    let listing = format!(
+        "[Function]
+- Slot Count: 6
+Instructions:
+- LoadK: REG(0) CONST(0)
+- LoadK: REG(1) CONST(1)
+- LoadK: REG(2) CONST(0)
+- ForPrep: REG(0) NUMBER(3)
+- GetGlobal: REG(4) CONST(2)
+- Move: REG(5) REG(3)
+- Call: REG(4) NUMBER(2) NUMBER(1)
+- ForLoop: REG(0) NUMBER(-4)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TNUMBER: 1.0
+- TNUMBER: 3.0
+- TSTRING: \"print\"
+{}[End Function]
+",
+        debug_info(
+            &[
+                ("(for index)", 3, 8),
+                ("(for limit)", 3, 8),
+                ("(for step)", 3, 8),
+                ("i", 4, 7),
+            ],
+            &[]
+        )
+    );
+    assert_eq!(
+        decompile(&listing),
+        "for i = 1, 3 do
+    print(i)
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_generic_for_loops() {
+    let listing = format!(
+        "[Function]
+- Slot Count: 7
+Instructions:
+- GetGlobal: REG(0) CONST(0)
+- GetGlobal: REG(1) CONST(1)
+- Call: REG(0) NUMBER(2) NUMBER(4)
+- Jmp: NUMBER(0) NUMBER(3)
+- GetGlobal: REG(5) CONST(2)
+- Move: REG(6) REG(3)
+- Call: REG(5) NUMBER(2) NUMBER(1)
+- TForLoop: REG(0) NUMBER(2)
+- Jmp: NUMBER(0) NUMBER(-5)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"pairs\"
+- TSTRING: \"t\"
+- TSTRING: \"print\"
+{}[End Function]
+",
+        debug_info(
+            &[
+                ("(for generator)", 3, 9),
+                ("(for state)", 3, 9),
+                ("(for control)", 3, 9),
+                ("k", 4, 7),
+                ("v", 4, 7),
+            ],
+            &[]
+        )
+    );
+    assert_eq!(
+        decompile(&listing),
+        "for k, v in pairs(t) do
+    print(k)
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_while_loops() {
+    let listing = "[Function]
+- Slot Count: 2
+Instructions:
+- GetGlobal: REG(0) CONST(0)
+- Lt: NUMBER(0) REG(0) CONST(1)
+- Jmp: NUMBER(0) NUMBER(4)
+- GetGlobal: REG(0) CONST(0)
+- Add: REG(0) REG(0) CONST(2)
+- SetGlobal: REG(0) CONST(0)
+- Jmp: NUMBER(0) NUMBER(-7)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"x\"
+- TNUMBER: 10.0
+- TNUMBER: 1.0
+[End Function]
+";
+    assert_eq!(
+        decompile(listing),
+        "while x < 10 do
+    x = x + 1
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_varargs() {
+    let listing = format!(
+        "[Function]
+- Variadic Argument Type: ISVARARG
+- Slot Count: 4
+Instructions:
+- Vararg: REG(0) NUMBER(3)
+- GetGlobal: REG(2) CONST(0)
+- Move: REG(3) REG(1)
+- Call: REG(2) NUMBER(2) NUMBER(1)
+- GetGlobal: REG(2) CONST(0)
+- Vararg: REG(3) NUMBER(0)
+- Call: REG(2) NUMBER(0) NUMBER(1)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"print\"
+{}[End Function]
+",
+        debug_info(&[("a", 1, 8), ("b", 1, 8)], &[])
+    );
+    assert_eq!(
+        decompile(&listing),
+        "local a, b = ...
+print(b)
+print(...)
+"
+    );
+}
+
+#[test]
+fn decompiles_closures_with_upvalues() {
+    let listing = format!(
+        "[Function]
+- Slot Count: 2
+Instructions:
+- LoadK: REG(0) CONST(0)
+- Closure: REG(1) NUMBER(0)
+- Move: REG(0) REG(0)
+- SetGlobal: REG(1) CONST(1)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TNUMBER: 0.0
+- TSTRING: \"inc\"
+{}[Function]
+- UpValue Count: 1
+- Slot Count: 1
+Instructions:
+- GetUpval: REG(0) NUMBER(0)
+- Add: REG(0) REG(0) CONST(0)
+- SetUpval: REG(0) NUMBER(0)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TNUMBER: 1.0
+{}[End Function]
+[End Function]
+",
+        debug_info(&[("count", 1, 5)], &[]),
+        debug_info(&[], &["count"])
+    );
+    assert_eq!(
+        decompile(&listing),
+        "local count = 0
+function inc()
+    count = count + 1
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_set_list() {
+    let listing = "[Function]
+- Slot Count: 4
+Instructions:
+- NewTable: REG(0) NUMBER(3) NUMBER(0)
+- LoadK: REG(1) CONST(0)
+- LoadK: REG(2) CONST(1)
+- LoadK: REG(3) CONST(2)
+- SetList: REG(0) NUMBER(3) NUMBER(1)
+- SetGlobal: REG(0) CONST(3)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TNUMBER: 1.0
+- TNUMBER: 2.0
+- TNUMBER: 3.0
+- TSTRING: \"t\"
+[End Function]
+";
+    assert_eq!(decompile(listing), "t = { 1, 2, 3 }\n");
+}
+
+#[test]
+fn keeps_varargs_out_of_registers() {
+    let listing = "[Function]
+- Slot Count: 1
+Instructions:
+- Closure: REG(0) NUMBER(0)
+- SetGlobal: REG(0) CONST(0)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"f\"
+[Function]
+- Parameter Count: 1
+- Variadic Argument Type: ISVARARG
+- Slot Count: 3
+Instructions:
+- GetField: REG(2) REG(1) CONST(0)
+- Return: REG(2) NUMBER(2)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"x\"
+[End Function]
+[End Function]
+";
+    assert_eq!(
+        decompile(listing),
+        "function f(r0, ...)
+    local r1
+    return r1.x
+end
+"
+    );
+}
+
+#[test]
+fn limits_parameters_to_the_slot_count() {
+    let listing = "[Function]
+- Slot Count: 1
+Instructions:
+- Closure: REG(0) NUMBER(0)
+- SetGlobal: REG(0) CONST(0)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TSTRING: \"f\"
+[Function]
+- Parameter Count: 4000000000
+- Slot Count: 2
+Instructions:
+- Return: REG(1) NUMBER(2)
+- Return: REG(0) NUMBER(1)
+Constants:
+[End Function]
+[End Function]
+";
+    assert_eq!(
+        decompile(listing),
+        "function f(r0, r1)
+    return r1
+end
+"
+    );
+}
+
+#[test]
+fn decompiles_structures() {
+    let mut file = read(include_bytes!("fixtures/structures.luac"));
+    let function = &mut file.main_function;
+    function.param_count = 1;
+    function.instructions = vec![
+        instruction(
+            HSOpCode::SetSlotN,
+            &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 24)],
+        ),
+        instruction(
+            HSOpCode::NewStruct,
+            &[
+                (HSOpArgMode::REG, 1),
+                (HSOpArgMode::NUMBER, 0),
+                (HSOpArgMode::NUMBER, 0),
+            ],
+        ),
+        instruction(
+            HSOpCode::Data,
+            &[(HSOpArgMode::NUMBER, 0), (HSOpArgMode::NUMBER, 20)],
+        ),
+        instruction(
+            HSOpCode::Return,
+            &[(HSOpArgMode::REG, 1), (HSOpArgMode::NUMBER, 2)],
+        ),
+    ];
+    assert_eq!(
+        HSDecompiler::new(&file).to_string(),
+        "r0.slot24 = nil
+return hmake Player {}
+"
+    );
+}
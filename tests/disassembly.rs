@@ -0,0 +1,228 @@
+mod common;
+
+use common::{assemble, instruction, read};
+use hkscdis_rs::loader::{
+    hs_function::{HSDisplayContext, HSFunction},
+    hs_opcodes::{HSOpArgMode, HSOpCode},
+};
+
+/// Returns the disassembled instructions of `function`, without colors.
+fn instructions(function: &HSFunction) -> String {
+    colored::control::set_override(false);
+    let text = function
+        .display_single(HSDisplayContext::default())
+        .to_string();
+    let start = text.find("Instructions:\n").unwrap() + "Instructions:\n".len();
+    let end = text.find("Debug Info:\n").unwrap_or(text.len() - 1);
+    text[start..end].to_string()
+}
+
+#[test]
+fn labels_jump_targets() {
+    let file = read(include_bytes!("fixtures/program.luac"));
+    assert_eq!(
+        instructions(&file.main_function),
+        "- [ 0] NewTable: REG(0) NUMBER(2) NUMBER(1)
+- [ 1] LoadK: REG(1) CONST(1)
+- [ 2] LoadK: REG(2) CONST(2)
+- [ 3] SetField: REG(0) CONST(x) CONST(3)
+- [ 4] SetList: REG(0) NUMBER(2) NUMBER(1) -> [1..2]
+- [ 5] LoadK: REG(1) CONST(1)
+- [ 6] LoadK: REG(2) CONST(10)
+- [ 7] LoadK: REG(3) CONST(1)
+- [ 8] ForPrep: REG(1:(for index)) NUMBER(12) -> L21
+L9:
+- [ 9] LtBk: NUMBER(0) CONST(5) REG(4:i)
+- [10] Jmp: NUMBER(0) NUMBER(7) -> L18 (may be skipped)
+- [11] GetField: REG(5) REG(0:t) CONST(x)
+- [12] Test: REG(5) NUMBER(0)
+- [13] Jmp: NUMBER(0) NUMBER(4) -> L18 (may be skipped)
+- [14] GetGlobal: REG(5) CONST(print)
+- [15] Move: REG(6) REG(4:i)
+- [16] Call: REG(5) NUMBER(2) NUMBER(1)
+- [17] Jmp: NUMBER(0) NUMBER(3) -> L21
+L18:
+- [18] GetGlobal: REG(5) CONST(print)
+- [19] LoadK: REG(6) CONST(no)
+- [20] Call: REG(5) NUMBER(2) NUMBER(1)
+L21:
+- [21] ForLoop: REG(1:(for index)) NUMBER(-13) -> L9
+- [22] GetGlobal: REG(1) CONST(pairs)
+- [23] Move: REG(2) REG(0:t)
+- [24] Call: REG(1) NUMBER(2) NUMBER(4)
+- [25] Jmp: NUMBER(0) NUMBER(4) -> L30
+L26:
+- [26] GetGlobal: REG(6) CONST(print)
+- [27] Move: REG(7) REG(4:k)
+- [28] Move: REG(8) REG(5:v)
+- [29] Call: REG(6) NUMBER(3) NUMBER(1)
+L30:
+- [30] TForLoop: REG(1:(for generator)) NUMBER(2)
+- [31] Jmp: NUMBER(0) NUMBER(-6) -> L26 (may be skipped)
+- [32] Closure: REG(1) NUMBER(0) -> 0/0
+L33:
+- [33] GetField: REG(2) REG(0:t) CONST(x)
+- [34] Lt: NUMBER(0) REG(2) CONST(10)
+- [35] Jmp: NUMBER(0) NUMBER(4) -> L40 (may be skipped)
+- [36] GetField: REG(2) REG(0:t) CONST(x)
+- [37] Add: REG(2) REG(2) CONST(1)
+- [38] SetField: REG(0:t) CONST(x) REG(2)
+- [39] Jmp: NUMBER(0) NUMBER(-7) -> L33
+L40:
+- [40] GetGlobal: REG(2) CONST(print)
+- [41] Move: REG(3) REG(1:add)
+- [42] LoadK: REG(4) CONST(1)
+- [43] LoadK: REG(5) CONST(2)
+- [44] Call: REG(3) NUMBER(3) NUMBER(0)
+- [45] Call: REG(2) NUMBER(0) NUMBER(1)
+- [46] Return: REG(0:t) NUMBER(1)
+"
+    );
+}
+
+#[test]
+fn shows_closures_with_their_captures() {
+    let mut file = read(include_bytes!("fixtures/closures.luac"));
+    let main = &file.main_function;
+    let child = |pc| {
+        main.closure_child(pc)
+            .map(|(index, child)| (index, child.id.as_str()))
+    };
+    assert_eq!(child(0), Some((0, "0/0")));
+    assert_eq!(child(1), None);
+    assert_eq!(child(2), Some((1, "0/1")));
+    assert_eq!(
+        instructions(main),
+        "- [0] Closure: REG(0) NUMBER(0) -> 0/0
+- [1] LoadK: REG(1) CONST(0)
+- [2] Closure: REG(2) NUMBER(1) -> 0/1
+- [3] Capture: REG(1:count) -> count
+- [4] SetGlobal: REG(2) CONST(inc)
+- [5] Return: REG(0:f) NUMBER(1)
+"
+    );
+
+    // Closures of missing children have no upvalues to capture.
+    file.main_function.instructions[2].args[1].value = 2;
+    let main = &file.main_function;
+    assert!(main.closure_child(2).is_none());
+    assert!(instructions(main).contains(
+        "- [2] Closure: REG(2) NUMBER(2) (out of range)
+- [3] Move: REG(0:f) REG(1:count)
+"
+    ));
+}
+
+#[test]
+fn names_registers_only_while_their_local_is_active() {
+    let file = assemble(
+        "[Function]
+- Slot Count: 3
+Instructions:
+- LoadK: REG(0) CONST(0)
+- LoadK: REG(1) CONST(0)
+- Move: REG(2) REG(0)
+- Move: REG(0) REG(1)
+- Return: REG(0) NUMBER(1)
+Constants:
+- TNUMBER: 1.0
+Debug Info:
+- Line Begin: 0
+- Line End: 0
+- Path: \"@test.lua\"
+- Function Name: \"\"
+- Lines:
+- Locals:
+   - \"a\": 1 4
+   - \"b\": 2 3
+   - \"c\": 3 5
+- UpValues:
+[End Function]
+",
+    );
+    assert_eq!(
+        instructions(&file.main_function),
+        "- [0] LoadK: REG(0) CONST(1)
+- [1] LoadK: REG(1) CONST(1)
+- [2] Move: REG(2) REG(0:a)
+- [3] Move: REG(0:a) REG(1:c)
+- [4] Return: REG(0:c) NUMBER(1)
+"
+    );
+}
+
+#[test]
+fn reads_data_extending_set_list_and_new_struct() {
+    let mut file = read(include_bytes!("fixtures/structures.luac"));
+    let data = |value| {
+        instruction(
+            HSOpCode::Data,
+            &[(HSOpArgMode::NUMBER, 0), (HSOpArgMode::NUMBER, value)],
+        )
+    };
+    let set_list = |batch| {
+        instruction(
+            HSOpCode::SetList,
+            &[
+                (HSOpArgMode::REG, 0),
+                (HSOpArgMode::NUMBER, 2),
+                (HSOpArgMode::NUMBER, batch),
+            ],
+        )
+    };
+    let new_struct = || {
+        instruction(
+            HSOpCode::NewStruct,
+            &[
+                (HSOpArgMode::REG, 1),
+                (HSOpArgMode::NUMBER, 0),
+                (HSOpArgMode::NUMBER, 0),
+            ],
+        )
+    };
+    file.main_function.instructions = vec![
+        set_list(0),
+        data(600),
+        set_list(3),
+        new_struct(),
+        data(20),
+        new_struct(),
+        data(7),
+        new_struct(),
+    ];
+    let main = &file.main_function;
+
+    // Batches too large for C are stored in the following `Data`.
+    assert_eq!(main.data(0), Some(600));
+    assert_eq!(main.set_list_batch(0), Some(600));
+    assert!(main.extends_previous(1));
+    assert_eq!(main.data(2), None);
+    assert_eq!(main.set_list_batch(2), Some(3));
+    assert!(!main.extends_previous(3));
+    assert_eq!(main.new_struct_id(3), Some(20));
+    assert!(main.extends_previous(4));
+    assert_eq!(main.new_struct_id(5), Some(7));
+    // A `NewStruct` without a following `Data` has no structure.
+    assert_eq!(main.data(7), None);
+    assert_eq!(main.new_struct_id(7), None);
+    assert_eq!(main.new_struct_id(0), None);
+    assert_eq!(main.set_list_batch(3), None);
+
+    let text = main
+        .display_single(HSDisplayContext {
+            structs: &file.structs,
+            ..HSDisplayContext::default()
+        })
+        .to_string();
+    for line in [
+        "- [0] SetList: REG(0) NUMBER(2) NUMBER(0) -> [29951..29952]\n",
+        "- [1] Data: NUMBER(0) NUMBER(600) (extends [0])\n",
+        "- [2] SetList: REG(0) NUMBER(2) NUMBER(3) -> [101..102]\n",
+        "- [3] NewStruct: REG(1) NUMBER(0) NUMBER(0) -> struct Player\n",
+        "- [4] Data: NUMBER(0) NUMBER(20) (extends [3])\n",
+        "- [5] NewStruct: REG(1) NUMBER(0) NUMBER(0) -> struct #7 (unknown)\n",
+        "- [7] NewStruct: REG(1) NUMBER(0) NUMBER(0)\n",
+    ] {
+        assert!(text.contains(line), "{line:?} missing from {text}");
+    }
+}
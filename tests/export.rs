@@ -0,0 +1,76 @@
+use hkscdis_rs::{
+    export::{emmylua::HSLuaStubs, header::HSCHeader},
+    loader::{
+        hs_opcodes::HSType,
+        hs_structure::{HSStructPrototype, HSStructSlot},
+    },
+};
+
+/// Returns a structure with number slots of the given names, at increasing positions.
+fn structure(name: &str, id: u64, slots: &[&str]) -> HSStructPrototype {
+    HSStructPrototype {
+        name: name.to_string(),
+        id,
+        slots: (16..)
+            .step_by(8)
+            .zip(slots)
+            .map(|(position, name)| HSStructSlot {
+                name: (*name).to_string(),
+                type_: HSType::TNUMBER,
+                position,
+                ..HSStructSlot::default()
+            })
+            .collect(),
+        ..HSStructPrototype::default()
+    }
+}
+
+#[test]
+fn writes_unique_c_identifiers() {
+    let structs = [
+        structure("a_b", 1, &["x_y", "x.y"]),
+        structure("a.b", 2, &[]),
+        structure("2d", 3, &["z"]),
+    ];
+    let header = HSCHeader::for_structs(&structs).to_string();
+    for define in [
+        "#define a_b_ID 1\n",
+        "#define a_b_x_y_OFFSET 16 ",
+        "#define a_b_x_y_2_OFFSET 24 ",
+        "#define a_b_2_ID 2\n",
+        "#define _2d_ID 3\n",
+        "#define _2d_z_OFFSET 16 ",
+    ] {
+        assert!(header.contains(define), "{define:?} missing from {header}");
+    }
+}
+
+#[test]
+fn breaks_up_comment_ends_in_names() {
+    let mut structs = [structure("Evil */ int x; /*", 1, &[])];
+    structs[0].inherited_structs = vec!["*/".to_string()];
+    let header = HSCHeader::for_structs(&structs).to_string();
+    assert!(header.contains("/* Evil * / int x; /* : * / */\n"));
+}
+
+#[test]
+fn quotes_field_names_that_are_not_identifiers() {
+    let structs = [structure(
+        "Player",
+        1,
+        &["health", "end", "max hp", "2x", "say \"hi\"\n"],
+    )];
+    let stubs = HSLuaStubs::for_structs(&structs).to_string();
+    assert_eq!(
+        stubs,
+        "---@meta
+
+---@class Player
+---@field health number
+---@field [\"end\"] number
+---@field [\"max hp\"] number
+---@field [\"2x\"] number
+---@field [\"say \\\"hi\\\"\\010\"] number
+"
+    );
+}
@@ -0,0 +1,154 @@
+use hkscdis_rs::loader::{
+    hs::HavokScriptFile,
+    hs_inheritance::{HSInheritance, HSInheritanceIssue},
+    hs_options::HSReadOptions,
+    hs_structure::HSStructPrototype,
+};
+
+const STRUCTURES: &[u8] = include_bytes!("fixtures/structures.luac");
+
+fn read(bytes: &[u8], enable_inheritance: Option<bool>) -> HavokScriptFile {
+    let options = HSReadOptions {
+        enable_inheritance,
+        ..HSReadOptions::default()
+    };
+    HavokScriptFile::from_bytes_with_options(bytes, &options).unwrap()
+}
+
+/// Returns the structures fixture written without the inheritance extension.
+fn without_inheritance() -> Vec<u8> {
+    let mut file = read(STRUCTURES, None);
+    for structure in &mut file.structs {
+        structure.inherited_structs.clear();
+        structure.inherited_count = 0;
+    }
+    file.to_bytes_with(false).unwrap()
+}
+
+#[test]
+fn detects_structures_with_inheritance() {
+    let file = read(STRUCTURES, None);
+    assert!(file.enable_inheritance);
+    assert!(file.inheritance_detected);
+    assert!(file.diagnostics.is_empty());
+    assert_eq!(file.structs[1].inherited_structs, ["Base"]);
+}
+
+#[test]
+fn detects_structures_without_inheritance() {
+    let bytes = without_inheritance();
+    let file = read(&bytes, None);
+    assert!(!file.enable_inheritance);
+    assert!(file.inheritance_detected);
+    assert_eq!(file.structs.len(), 2);
+    assert_eq!(file.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn keeps_the_given_layout() {
+    let file = read(STRUCTURES, Some(true));
+    assert!(file.enable_inheritance);
+    assert!(!file.inheritance_detected);
+    let options = HSReadOptions {
+        enable_inheritance: Some(false),
+        ..HSReadOptions::default()
+    };
+    assert!(HavokScriptFile::from_bytes_with_options(STRUCTURES, &options).is_err());
+}
+
+/// Returns the names of the flattened slots of the structure at `index`, along with the name of
+/// the structure declaring each of them.
+fn flattened_slots(file: &HavokScriptFile, index: usize) -> Vec<(&str, &str)> {
+    file.flattened_slots(index)
+        .into_iter()
+        .map(|member| (member.slot.name.as_str(), member.origin.name.as_str()))
+        .collect()
+}
+
+#[test]
+fn flattens_inherited_slots() {
+    let file = read(STRUCTURES, None);
+    assert!(file.inheritance.issues.is_empty());
+    assert_eq!(file.inheritance.parents, [vec![], vec![0]]);
+    assert_eq!(
+        flattened_slots(&file, 1),
+        [
+            ("health", "Player"),
+            ("name", "Player"),
+            ("base", "Player"),
+            ("a", "Base"),
+        ]
+    );
+}
+
+#[test]
+fn reports_missing_parents() {
+    let mut file = read(STRUCTURES, None);
+    file.structs[1].inherited_structs.push("Ghost".to_string());
+    file.resolve_inheritance();
+    assert_eq!(
+        file.inheritance.issues,
+        [HSInheritanceIssue::MissingParent {
+            structure: "Player".to_string(),
+            parent: "Ghost".to_string(),
+        }]
+    );
+    assert_eq!(file.inheritance.parents[1], [0]);
+    assert_eq!(flattened_slots(&file, 1).len(), 4);
+}
+
+#[test]
+fn reports_cycles() {
+    let mut file = read(STRUCTURES, None);
+    file.structs[0].inherited_structs.push("Player".to_string());
+    file.resolve_inheritance();
+    assert_eq!(
+        file.inheritance.issues,
+        [HSInheritanceIssue::Cycle {
+            structures: vec!["Base".to_string(), "Player".to_string(), "Base".to_string()],
+        }]
+    );
+    assert_eq!(
+        flattened_slots(&file, 0),
+        [
+            ("a", "Base"),
+            ("health", "Player"),
+            ("name", "Player"),
+            ("base", "Player"),
+        ]
+    );
+}
+
+#[test]
+fn skips_structures_removed_since_resolving() {
+    let mut file = read(STRUCTURES, None);
+    file.structs.push(HSStructPrototype {
+        name: "Extra".to_string(),
+        ..HSStructPrototype::default()
+    });
+    file.structs[1].inherited_structs.push("Extra".to_string());
+    file.resolve_inheritance();
+    assert_eq!(file.inheritance.parents[1], [0, 2]);
+    file.structs.pop();
+    assert_eq!(flattened_slots(&file, 1).len(), 4);
+    assert!(file.to_string().contains("[Structure: Player"));
+}
+
+#[test]
+fn resolves_long_inheritance_chains() {
+    let structs = (0..100_000)
+        .map(|index| HSStructPrototype {
+            name: format!("S{index}"),
+            inherited_structs: vec![format!("S{}", index + 1)],
+            ..HSStructPrototype::default()
+        })
+        .collect::<Vec<_>>();
+    let inheritance = HSInheritance::resolve(&structs);
+    assert_eq!(
+        inheritance.issues,
+        [HSInheritanceIssue::MissingParent {
+            structure: "S99999".to_string(),
+            parent: "S100000".to_string(),
+        }]
+    );
+}
@@ -0,0 +1,74 @@
+use hkscdis_rs::{
+    assembler::{assemble, assemble_with_profile, listing::HSListing},
+    loader::{
+        hs::HavokScriptFile, hs_function::HSFunction, hs_opcodes::HSOpCode,
+        hs_options::HSReadOptions, hs_profile::HSOpcodeProfile,
+    },
+};
+
+const PROGRAM: &[u8] = include_bytes!("fixtures/program.luac");
+
+/// Returns the built-in profile with the numbers of `GetGlobal` and `Move` swapped.
+fn swapped() -> HSOpcodeProfile {
+    let mut profile = HSOpcodeProfile::builtin().clone();
+    profile.name = "swapped".to_string();
+    profile.table.swap(6, 7);
+    profile
+}
+
+fn read(bytes: &[u8], profile: HSOpcodeProfile) -> HavokScriptFile {
+    let options = HSReadOptions {
+        profile,
+        ..HSReadOptions::default()
+    };
+    HavokScriptFile::from_bytes_with_options(bytes, &options).unwrap()
+}
+
+fn opcodes(function: &HSFunction) -> Vec<HSOpCode> {
+    let mut opcodes = function
+        .instructions
+        .iter()
+        .map(|instruction| instruction.mode)
+        .collect::<Vec<_>>();
+    for child in &function.child_functions {
+        opcodes.extend(self::opcodes(child));
+    }
+    opcodes
+}
+
+/// Returns the program fixture encoded with the swapped profile.
+fn swapped_program() -> Vec<u8> {
+    let mut file = read(PROGRAM, HSOpcodeProfile::default());
+    file.profile = swapped();
+    let bytes = file.to_bytes().unwrap();
+    assert_ne!(bytes, PROGRAM);
+    bytes
+}
+
+#[test]
+fn writes_with_the_profile_it_was_read_with() {
+    let bytes = swapped_program();
+    let file = read(&bytes, swapped());
+    assert_eq!(
+        opcodes(&file.main_function),
+        opcodes(&read(PROGRAM, HSOpcodeProfile::default()).main_function)
+    );
+    assert_eq!(file.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn assembles_listings_with_their_profile() {
+    let bytes = swapped_program();
+    let listing = HSListing::new(&read(&bytes, swapped())).to_string();
+    assert!(listing.contains("- Opcode Profile: swapped\n"));
+    assert!(assemble(&listing).is_err());
+    assert_eq!(assemble_with_profile(&listing, &swapped()).unwrap(), bytes);
+}
+
+#[test]
+fn fails_to_write_opcodes_missing_from_the_profile() {
+    let mut file = read(PROGRAM, HSOpcodeProfile::default());
+    file.profile.table[7] = None;
+    let error = file.to_bytes().unwrap_err();
+    assert!(error.to_string().contains("Move"));
+}
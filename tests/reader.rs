@@ -0,0 +1,160 @@
+use hkscdis_rs::{
+    common::errors::HkscError,
+    loader::{hs::HavokScriptFile, hs_opcodes::HSOpCode, hs_options::HSReadOptions},
+};
+
+const PROGRAM: &[u8] = include_bytes!("fixtures/program.luac");
+
+/// Offset of the first instruction of the main function of `PROGRAM`.
+const FIRST_INSTRUCTION: usize = 0x28;
+
+/// Returns `PROGRAM` with the opcode of the instruction at `pc` of the main function replaced
+/// by 127, which no opcode is numbered with.
+fn corrupt_opcode(pc: usize) -> Vec<u8> {
+    let mut bytes = PROGRAM.to_vec();
+    bytes[FIRST_INSTRUCTION + pc * 4] |= 0xFE;
+    bytes
+}
+
+fn read(bytes: &[u8], options: &HSReadOptions) -> Result<HavokScriptFile, HkscError> {
+    HavokScriptFile::from_bytes_with_options(bytes, options)
+}
+
+#[test]
+fn rejects_unknown_opcodes() {
+    let Err(error) = read(&corrupt_opcode(3), &HSReadOptions::default()) else {
+        panic!("read a file with an unknown opcode");
+    };
+    let HkscError::Context { source, .. } = error else {
+        panic!("{error} has no context");
+    };
+    assert!(
+        matches!(
+            *source,
+            HkscError::UnknownOpcode {
+                opcode: 127,
+                raw: 0xFE05_0300,
+                ..
+            }
+        ),
+        "{source}"
+    );
+}
+
+#[test]
+fn keeps_unknown_opcodes_when_lenient() {
+    let bytes = corrupt_opcode(3);
+    let options = HSReadOptions {
+        lenient: true,
+        ..HSReadOptions::default()
+    };
+    let file = read(&bytes, &options).unwrap();
+    let instructions = &file.main_function.instructions;
+    assert_eq!(instructions.len(), 47);
+    assert_eq!(instructions[3].mode, HSOpCode::Unknown);
+    assert!(instructions[3].args.is_empty());
+    assert_eq!(instructions[3].raw >> 25, 127);
+    assert_eq!(instructions[4].mode, HSOpCode::SetList);
+    assert!(file.diagnostics.is_empty());
+}
+
+/// Returns the offset, path and message of an error wrapped in `HkscError::Context`.
+fn context(error: HkscError) -> (u64, Vec<String>, String) {
+    let HkscError::Context {
+        offset,
+        path,
+        source,
+    } = error
+    else {
+        panic!("{error} has no context");
+    };
+    (offset, path, source.to_string())
+}
+
+#[test]
+fn reports_where_reading_failed() {
+    let Err(error) = read(&corrupt_opcode(3), &HSReadOptions::default()) else {
+        panic!("read a file with an unknown opcode");
+    };
+    assert_eq!(
+        error.to_string(),
+        "Unknown opcode 127 in instruction 0xfe050300! (in main > instructions[3], at offset 0x34)"
+    );
+    assert_eq!(
+        context(error),
+        (
+            0x34,
+            vec!["main".to_string(), "instructions[3]".to_string()],
+            "Unknown opcode 127 in instruction 0xfe050300!".to_string()
+        )
+    );
+
+    // The file ends in the middle of the first instruction of the child function.
+    let Err(error) = read(&PROGRAM[..0x2a2], &HSReadOptions::default()) else {
+        panic!("read a truncated file");
+    };
+    let (offset, path, _) = context(error);
+    assert_eq!(offset, 0x2a2);
+    assert_eq!(path, ["main", "child[0]", "instructions[0]"]);
+}
+
+#[test]
+fn recovers_truncated_files() {
+    let options = HSReadOptions {
+        recover: true,
+        ..HSReadOptions::default()
+    };
+    let file = read(&PROGRAM[..0x2a2], &options).unwrap_or_else(|error| panic!("{error}"));
+    let main = &file.main_function;
+    assert_eq!(main.instructions.len(), 47);
+    assert!(main.has_debug_info);
+    assert_eq!(main.child_functions.len(), 1);
+    assert_eq!(main.child_functions[0].instruction_count, 3);
+    assert!(main.child_functions[0].instructions.is_empty());
+    assert!(file.structs.is_empty());
+    let diagnostics = file
+        .diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        diagnostics,
+        [
+            "Failed to read from buffer! (in main > child[0] > instructions[0], at offset 0x2a2)",
+            "Functions fully read: 0 of 2.",
+            "The structures were skipped."
+        ]
+    );
+
+    let file = read(&PROGRAM[..0x100], &options).unwrap_or_else(|error| panic!("{error}"));
+    let main = &file.main_function;
+    assert_eq!(main.instructions.len(), 47);
+    assert!(!main.has_debug_info);
+    assert_eq!(file.diagnostics[0].path, ["main", "constants[2]"]);
+    assert_eq!(file.diagnostics[0].offset, Some(0x100));
+}
+
+#[test]
+fn finds_functions_by_id() {
+    let file = read(
+        include_bytes!("fixtures/closures.luac"),
+        &HSReadOptions::default(),
+    )
+    .unwrap_or_else(|error| panic!("{error}"));
+    let main = &file.main_function;
+    assert_eq!((main.start_offset, main.end_offset), (0x12, 0x292));
+
+    let inc = main.find("0/1").unwrap();
+    assert_eq!(inc.id, "0/1");
+    assert_eq!(inc.debug_info.up_values[0], "count");
+    assert_eq!((inc.start_offset, inc.end_offset), (0x211, 0x292));
+    assert_eq!(inc.depth, 1);
+    // Functions are laid out one after the other, children before the end of their parent.
+    let f = main.find("0/0").unwrap();
+    assert_eq!((f.start_offset, f.end_offset), (0xb9, 0x211));
+    assert!(std::ptr::eq(main.find("0").unwrap(), main));
+
+    for id in ["", "1", "0/2", "0/1/0", "0/", "/0"] {
+        assert!(main.find(id).is_none(), "{id:?} was found");
+    }
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::read;
+use serde_json::json;
+
+#[test]
+fn serializes_files() {
+    let file = read(include_bytes!("fixtures/structures.luac"));
+    let value = serde_json::to_value(&file).unwrap();
+    assert_eq!(
+        value["header"]["features"],
+        "MEMOIZATION | STRUCTURES | DOUBLES"
+    );
+    assert_eq!(
+        value["enums"][1],
+        json!({ "value": 1, "length": 8, "name": "TBOOLEAN" })
+    );
+    assert_eq!(value["enable_inheritance"], true);
+    assert_eq!(value["inheritance"]["parents"], json!([[], [0]]));
+    assert_eq!(value["diagnostics"], json!([]));
+    // The encoding and opcode profile are only needed to write the file back.
+    assert!(value.get("encoding").is_none());
+    assert!(value.get("profile").is_none());
+
+    let player = &value["structs"][1];
+    assert_eq!(player["name"], "Player");
+    assert_eq!(player["id"], 20);
+    assert_eq!(player["inherited_structs"], json!(["Base"]));
+    assert_eq!(
+        player["slots"][2],
+        json!({
+            "name": "base",
+            "struct_id": 21,
+            "type": "TSTRUCT",
+            "reserved": 0,
+            "position": 32
+        })
+    );
+
+    let main = &value["main_function"];
+    assert_eq!(main["var_arg"], "ISVARARG");
+    assert_eq!(main["id"], "0");
+    assert_eq!(
+        main["constants"],
+        json!([{ "type": "TNUMBER", "value": 1.5 }])
+    );
+    assert_eq!(main["child_functions"], json!([]));
+}
+
+#[test]
+fn serializes_instructions_and_debug_info() {
+    let file = read(include_bytes!("fixtures/program.luac"));
+    let value = serde_json::to_value(&file).unwrap();
+    let main = &value["main_function"];
+    assert_eq!(
+        main["instructions"][0],
+        json!({
+            "mode": "NewTable",
+            "args": [
+                { "mode": "REG", "value": 0 },
+                { "mode": "NUMBER", "value": 2 },
+                { "mode": "NUMBER", "value": 1 }
+            ],
+            "raw": 1_745_092_864
+        })
+    );
+    assert_eq!(
+        main["constants"][2],
+        json!({ "type": "TSTRING", "value": "x" })
+    );
+    assert_eq!(
+        main["debug_info"]["locals"][0],
+        json!({ "local_name": "t", "start": 5, "end": 47 })
+    );
+    assert_eq!(main["child_functions"][0]["id"], "0/0");
+}
@@ -0,0 +1,98 @@
+use hkscdis_rs::loader::{
+    hs_debug::HSFunctionDebugInfo, hs_function::HSFunction, hs_source::HSSources,
+};
+
+use std::{fs, path::PathBuf};
+
+/// Returns a function whose children come from each chunk name.
+fn chunks(chunk_names: &[&str]) -> HSFunction {
+    HSFunction {
+        child_functions: chunk_names
+            .iter()
+            .map(|chunk_name| HSFunction {
+                has_debug_info: true,
+                debug_info: HSFunctionDebugInfo {
+                    path: (*chunk_name).to_string(),
+                    ..HSFunctionDebugInfo::default()
+                },
+                ..HSFunction::default()
+            })
+            .collect(),
+        ..HSFunction::default()
+    }
+}
+
+/// Creates an empty directory for the test `name`, holding the source root `root` next to a
+/// `secret.lua` that chunk names must not reach.
+fn directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("hkscdis-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(directory.join("root/scripts")).unwrap();
+    fs::write(directory.join("secret.lua"), "secret\n").unwrap();
+    fs::write(
+        directory.join("root/scripts/player.lua"),
+        "local player = {}\nreturn player\n",
+    )
+    .unwrap();
+    fs::write(directory.join("root/util.lua"), "util\n").unwrap();
+    fs::write(directory.join("root/secret.lua"), "inside\n").unwrap();
+    directory
+}
+
+#[test]
+fn resolves_chunk_names_inside_the_root() {
+    let directory = directory("resolve");
+    let parent = directory.to_string_lossy().into_owned();
+    let chunk_names = [
+        "@scripts/player.lua".to_string(),
+        "@./scripts/player.lua".to_string(),
+        "@build/util.lua".to_string(),
+        "@../secret.lua".to_string(),
+        format!("@{parent}/secret.lua"),
+        "@scripts/missing.lua".to_string(),
+        "=stdin".to_string(),
+        "@".to_string(),
+    ];
+    let chunk_names = chunk_names.iter().map(String::as_str).collect::<Vec<_>>();
+    let sources = HSSources::load(&directory.join("root"), &chunks(&chunk_names));
+
+    // Relative paths are looked up below the root.
+    assert_eq!(
+        sources.line("@scripts/player.lua", 1),
+        Some("local player = {}")
+    );
+    assert_eq!(
+        sources.line("@scripts/player.lua", 2),
+        Some("return player")
+    );
+    assert_eq!(
+        sources.line("@./scripts/player.lua", 2),
+        Some("return player")
+    );
+    assert_eq!(sources.line("@scripts/player.lua", 0), None);
+    assert_eq!(sources.line("@scripts/player.lua", 3), None);
+    // Paths that don't exist below the root fall back to their file name.
+    assert_eq!(sources.line("@build/util.lua", 1), Some("util"));
+    // Names leaving the root only find files of the same name inside it.
+    assert_eq!(sources.line("@../secret.lua", 1), Some("inside"));
+    assert_eq!(sources.line(chunk_names[4], 1), Some("inside"));
+
+    assert_eq!(sources.missing(), ["=stdin", "@", "@scripts/missing.lua"]);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn ignores_files_outside_the_root() {
+    let directory = directory("outside");
+    fs::remove_file(directory.join("root/secret.lua")).unwrap();
+    let absolute = format!("@{}", directory.join("secret.lua").to_string_lossy());
+    let chunk_names = ["@../secret.lua", "@scripts/../../secret.lua", &absolute];
+    let sources = HSSources::load(&directory.join("root"), &chunks(&chunk_names));
+    for chunk_name in chunk_names {
+        assert_eq!(sources.line(chunk_name, 1), None, "{chunk_name} was read");
+    }
+    let mut missing = chunk_names.to_vec();
+    missing.sort_unstable();
+    assert_eq!(sources.missing(), missing);
+    fs::remove_dir_all(directory).unwrap();
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use common::instruction;
+use hkscdis_rs::loader::{
+    hs::HavokScriptFile,
+    hs_instruction::HSInstruction,
+    hs_opcodes::{HSOpArgMode, HSOpCode, HSType},
+};
+
+/// Returns the structures fixture, whose structure `Player` with id 20 inherits from `Base`.
+fn read() -> HavokScriptFile {
+    common::read(include_bytes!("fixtures/structures.luac"))
+}
+
+/// Returns a `CheckType` asserting that `register` holds a `Player`.
+fn check_player(register: i32) -> HSInstruction {
+    instruction(
+        HSOpCode::CheckType,
+        &[(HSOpArgMode::REG, register), (HSOpArgMode::NUMBER, 20)],
+    )
+}
+
+fn get_slot(register: i32, object: i32, position: i32) -> HSInstruction {
+    instruction(
+        HSOpCode::GetSlot,
+        &[
+            (HSOpArgMode::REG, register),
+            (HSOpArgMode::REG, object),
+            (HSOpArgMode::NUMBER, position),
+        ],
+    )
+}
+
+fn test(register: i32) -> HSInstruction {
+    instruction(
+        HSOpCode::Test,
+        &[(HSOpArgMode::REG, register), (HSOpArgMode::NUMBER, 0)],
+    )
+}
+
+fn jump(offset: i32) -> HSInstruction {
+    instruction(
+        HSOpCode::Jmp,
+        &[(HSOpArgMode::NUMBER, 0), (HSOpArgMode::NUMBER, offset)],
+    )
+}
+
+/// Returns the name of the structure known for the object of each instruction of the main
+/// function.
+fn slot_structs(file: &HavokScriptFile) -> Vec<Option<&str>> {
+    file.main_function
+        .slot_structs(&file.structs, &file.inheritance)
+        .into_iter()
+        .map(|structure| structure.map(|structure| structure.name.as_str()))
+        .collect()
+}
+
+#[test]
+fn forgets_structures_known_on_only_some_paths() {
+    let mut file = read();
+    file.main_function.instructions = vec![
+        test(1),
+        jump(1),
+        check_player(0),
+        get_slot(2, 0, 24),
+        check_player(0),
+        test(1),
+        jump(1),
+        instruction(
+            HSOpCode::LoadK,
+            &[(HSOpArgMode::REG, 2), (HSOpArgMode::CONST, 0)],
+        ),
+        get_slot(2, 0, 24),
+    ];
+    assert_eq!(
+        slot_structs(&file),
+        [
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some("Player")
+        ]
+    );
+}
+
+#[test]
+fn finds_inherited_slots() {
+    let mut file = read();
+    let slot = &mut file.structs[0].slots[0];
+    slot.position = 40;
+    slot.type_ = HSType::TSTRUCT;
+    slot.struct_id = 21;
+    file.main_function.instructions = vec![check_player(0), get_slot(1, 0, 40), get_slot(2, 1, 40)];
+    assert_eq!(slot_structs(&file), [None, Some("Player"), Some("Base")]);
+    let member = file.inheritance.slot_at(&file.structs, 1, 40).unwrap();
+    assert_eq!(member.slot.name, "a");
+    assert_eq!(member.origin.name, "Base");
+    assert!(file.inheritance.slot_at(&file.structs, 1, 48).is_none());
+}
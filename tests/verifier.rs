@@ -0,0 +1,215 @@
+mod common;
+
+use common::{instruction, read};
+use hkscdis_rs::{
+    loader::{
+        hs::HavokScriptFile,
+        hs_constant::HSConstant,
+        hs_function::HSFunction,
+        hs_instruction::HSInstruction,
+        hs_opcodes::{HSOpArgMode, HSOpCode},
+        hs_options::HSReadOptions,
+    },
+    verifier::{HSVerifyReport, HSViolationKind},
+};
+
+fn ret() -> HSInstruction {
+    instruction(
+        HSOpCode::Return,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 1)],
+    )
+}
+
+/// Returns a function with 2 registers and 1 constant running `instructions` and returning.
+fn function(instructions: Vec<HSInstruction>) -> HSFunction {
+    let mut function = HSFunction {
+        slot_count: 2,
+        constants: vec![HSConstant::default()],
+        instructions,
+        ..HSFunction::default()
+    };
+    function.instructions.push(ret());
+    function.instruction_count = u64::try_from(function.instructions.len()).unwrap();
+    function
+}
+
+/// Returns the violations of `function`, with the index of the instruction they were found at.
+fn violations(function: &HSFunction) -> Vec<(Option<usize>, HSViolationKind)> {
+    HSVerifyReport::for_function(function, &[], &["main".to_string()])
+        .violations
+        .into_iter()
+        .map(|violation| (violation.pc, violation.kind))
+        .collect()
+}
+
+#[test]
+fn accepts_consistent_functions() {
+    let function = function(vec![instruction(
+        HSOpCode::LoadK,
+        &[(HSOpArgMode::REG, 1), (HSOpArgMode::CONST, 0)],
+    )]);
+    assert_eq!(violations(&function), []);
+}
+
+#[test]
+fn reports_registers_out_of_range() {
+    let function = function(vec![instruction(
+        HSOpCode::Move,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::REG, 2)],
+    )]);
+    assert_eq!(
+        violations(&function),
+        [(
+            Some(0),
+            HSViolationKind::RegisterOutOfRange {
+                register: 2,
+                slot_count: 2
+            }
+        )]
+    );
+}
+
+#[test]
+fn reports_constants_out_of_range() {
+    let function = function(vec![instruction(
+        HSOpCode::LoadK,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::CONST, 1)],
+    )]);
+    assert_eq!(
+        violations(&function),
+        [(
+            Some(0),
+            HSViolationKind::ConstantOutOfRange {
+                constant: 1,
+                constant_count: 1
+            }
+        )]
+    );
+}
+
+#[test]
+fn reports_closures_out_of_range() {
+    let function = function(vec![instruction(
+        HSOpCode::Closure,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 0)],
+    )]);
+    assert_eq!(
+        violations(&function),
+        [(
+            Some(0),
+            HSViolationKind::ChildOutOfRange {
+                child: 0,
+                child_count: 0
+            }
+        )]
+    );
+}
+
+#[test]
+fn reports_upvalues_out_of_range() {
+    let function = function(vec![instruction(
+        HSOpCode::GetUpval,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 1)],
+    )]);
+    assert_eq!(
+        violations(&function),
+        [(
+            Some(0),
+            HSViolationKind::UpvalueOutOfRange {
+                up_value: 1,
+                up_value_count: 0
+            }
+        )]
+    );
+}
+
+#[test]
+fn reports_jumps_out_of_range() {
+    let function = function(vec![instruction(
+        HSOpCode::Jmp,
+        &[(HSOpArgMode::NUMBER, 0), (HSOpArgMode::NUMBER, 5)],
+    )]);
+    assert_eq!(
+        violations(&function),
+        [(
+            Some(0),
+            HSViolationKind::JumpOutOfRange {
+                target: 6,
+                instruction_count: 2
+            }
+        )]
+    );
+}
+
+#[test]
+fn reports_unknown_type_codes() {
+    let function = function(vec![instruction(
+        HSOpCode::CheckType,
+        &[(HSOpArgMode::REG, 0), (HSOpArgMode::NUMBER, 77)],
+    )]);
+    assert_eq!(
+        violations(&function),
+        [(Some(0), HSViolationKind::UnknownTypeCode { code: 77 })]
+    );
+}
+
+#[test]
+fn reports_unknown_opcodes() {
+    let function = function(vec![HSInstruction {
+        mode: HSOpCode::Unknown,
+        args: Vec::new(),
+        raw: 0xfe00_0000,
+    }]);
+    assert_eq!(
+        violations(&function),
+        [(Some(0), HSViolationKind::UnknownOpcode { raw: 0xfe00_0000 })]
+    );
+}
+
+#[test]
+fn reports_missing_returns() {
+    let mut function = function(Vec::new());
+    function.instructions.clear();
+    assert_eq!(
+        violations(&function),
+        [(None, HSViolationKind::MissingReturn)]
+    );
+}
+
+#[test]
+fn reports_line_count_mismatches() {
+    let mut function = function(Vec::new());
+    function.has_debug_info = true;
+    function.debug_info.lines = vec![1, 2];
+    assert_eq!(
+        violations(&function),
+        [(
+            None,
+            HSViolationKind::LineCountMismatch {
+                line_count: 2,
+                instruction_count: 1
+            }
+        )]
+    );
+}
+
+#[test]
+fn fails_partially_read_files() {
+    let bytes = include_bytes!("fixtures/program.luac");
+    let options = HSReadOptions {
+        recover: true,
+        ..HSReadOptions::default()
+    };
+    let file = HavokScriptFile::from_bytes_with_options(&bytes[..600], &options).unwrap();
+    assert!(!file.diagnostics.is_empty());
+    let report = HSVerifyReport::new(&file);
+    assert!(report.incomplete);
+    assert!(!report.is_ok());
+}
+
+#[test]
+fn verifies_function_trees_by_id() {
+    let file = read(include_bytes!("fixtures/program.luac"));
+    assert!(HSVerifyReport::for_id(&file, "0/0").unwrap().is_ok());
+    assert!(HSVerifyReport::for_id(&file, "0/1").is_none());
+}
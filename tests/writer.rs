@@ -0,0 +1,28 @@
+use hkscdis_rs::loader::{hs::HavokScriptFile, hs_options::HSReadOptions};
+
+fn round_trip(bytes: &[u8]) {
+    let file = HavokScriptFile::from_bytes_with_options(bytes, &HSReadOptions::default()).unwrap();
+    assert!(file.diagnostics.is_empty());
+    assert_eq!(file.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn writes_functions_back_unchanged() {
+    round_trip(include_bytes!("fixtures/program.luac"));
+}
+
+#[test]
+fn writes_structures_back_unchanged() {
+    round_trip(include_bytes!("fixtures/structures.luac"));
+}
+
+#[test]
+fn writes_8_byte_integers_back_unchanged() {
+    let listing =
+        include_str!("fixtures/program.lst").replace("- Integer Size: 4", "- Integer Size: 8");
+    let bytes = hkscdis_rs::assembler::assemble(&listing).unwrap();
+    let file = HavokScriptFile::from_bytes_with_options(&bytes, &HSReadOptions::default()).unwrap();
+    assert_eq!(file.header.int_size, 8);
+    assert_eq!(file.main_function.debug_info.locals.len(), 11);
+    assert_eq!(file.to_bytes().unwrap(), bytes);
+}